@@ -0,0 +1,96 @@
+use nightshade::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const TRANSITION_SECONDS: f32 = 0.8;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub fov_degrees: f32,
+}
+
+impl Bookmark {
+    pub fn new(name: String, position: Vec3, rotation: Quat, y_fov_rad: f32) -> Self {
+        Self {
+            name,
+            position: position.into(),
+            rotation: rotation.coords.into(),
+            fov_degrees: y_fov_rad.to_degrees(),
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        Vec3::from(self.position)
+    }
+
+    pub fn rotation(&self) -> Quat {
+        nalgebra_glm::quat_normalize(&Quat::from(Vec4::from(self.rotation)))
+    }
+
+    pub fn y_fov_rad(&self) -> f32 {
+        self.fov_degrees.clamp(1.0, 179.0).to_radians()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    pub bookmarks: Vec<Bookmark>,
+}
+
+pub struct BookmarkTransition {
+    from: Bookmark,
+    to: Bookmark,
+    elapsed: f32,
+}
+
+impl BookmarkTransition {
+    pub fn new(from: Bookmark, to: Bookmark) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn advance(&mut self, delta_time: f32) -> (Vec3, Quat, f32) {
+        self.elapsed += delta_time;
+        let t = (self.elapsed / TRANSITION_SECONDS).clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        (
+            self.from.position().lerp(&self.to.position(), eased),
+            nalgebra_glm::quat_slerp(&self.from.rotation(), &self.to.rotation(), eased),
+            self.from.y_fov_rad() + (self.to.y_fov_rad() - self.from.y_fov_rad()) * eased,
+        )
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= TRANSITION_SECONDS
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn bookmarks_path(ply_path: &std::path::Path) -> std::path::PathBuf {
+    ply_path.with_extension("bookmarks.json")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_bookmarks(path: &std::path::Path) -> Option<Bookmarks> {
+    let bytes = std::fs::read(path).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(bookmarks) => Some(bookmarks),
+        Err(error) => {
+            eprintln!("Failed to parse {}: {error}", path.display());
+            None
+        }
+    }
+}
+
+pub fn save_bookmarks(
+    path: &std::path::Path,
+    bookmarks: &Bookmarks,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, serde_json::to_vec_pretty(bookmarks)?)?;
+    Ok(())
+}
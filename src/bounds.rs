@@ -0,0 +1,53 @@
+use crate::gaussian::GpuGaussian;
+use nightshade::prelude::*;
+
+const LOWER_PERCENTILE: f32 = 0.02;
+const UPPER_PERCENTILE: f32 = 0.98;
+const MINIMUM_RADIUS: f32 = 0.01;
+
+#[derive(Copy, Clone)]
+pub struct SceneBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl SceneBounds {
+    pub fn from_gaussians(gaussians: &[GpuGaussian]) -> Option<Self> {
+        let axis_range = |axis: usize| {
+            let mut values: Vec<f32> = gaussians
+                .iter()
+                .map(|gaussian| gaussian.position[axis])
+                .filter(|value| value.is_finite())
+                .collect();
+            if values.is_empty() {
+                return None;
+            }
+            let last = values.len() - 1;
+            let lower_index = (last as f32 * LOWER_PERCENTILE).round() as usize;
+            let upper_index = (last as f32 * UPPER_PERCENTILE).round() as usize;
+            let lower = *values.select_nth_unstable_by(lower_index, f32::total_cmp).1;
+            let upper = *values.select_nth_unstable_by(upper_index, f32::total_cmp).1;
+            Some((lower, upper))
+        };
+
+        let (min_x, max_x) = axis_range(0)?;
+        let (min_y, max_y) = axis_range(1)?;
+        let (min_z, max_z) = axis_range(2)?;
+        Some(Self {
+            min: Vec3::new(min_x, min_y, min_z),
+            max: Vec3::new(max_x, max_y, max_z),
+        })
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn radius(&self) -> f32 {
+        ((self.max - self.min).norm() * 0.5).max(MINIMUM_RADIUS)
+    }
+
+    pub fn framing_distance(&self, y_fov_rad: f32) -> f32 {
+        self.radius() / (y_fov_rad * 0.5).sin()
+    }
+}
@@ -0,0 +1,172 @@
+use nightshade::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const TURNTABLE_KEYFRAMES: usize = 36;
+
+#[derive(Copy, Clone)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub y_fov_rad: f32,
+}
+
+impl CameraPose {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn matrices(&self, width: u32, height: u32) -> (Mat4, Mat4) {
+        let projection = PerspectiveCamera {
+            y_fov_rad: self.y_fov_rad,
+            ..Default::default()
+        }
+        .matrix_with_aspect(width as f32 / height.max(1) as f32);
+        (self.view_matrix(), projection)
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        nalgebra_glm::look_at(&self.position, &self.target, &Vec3::y())
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    pub fov_degrees: f32,
+}
+
+impl CameraKeyframe {
+    pub fn from_pose(time: f32, pose: &CameraPose) -> Self {
+        Self {
+            time,
+            position: pose.position.into(),
+            target: pose.target.into(),
+            fov_degrees: pose.y_fov_rad.to_degrees(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    pub fn turntable(
+        focus: Vec3,
+        radius: f32,
+        pitch: f32,
+        y_fov_rad: f32,
+        duration_seconds: f32,
+    ) -> Self {
+        let keyframes = (0..=TURNTABLE_KEYFRAMES)
+            .map(|index| {
+                let fraction = index as f32 / TURNTABLE_KEYFRAMES as f32;
+                let yaw = fraction * std::f32::consts::TAU;
+                let (position, _) =
+                    nightshade::ecs::camera::compute_pan_orbit_transform(focus, yaw, pitch, radius);
+                CameraKeyframe::from_pose(
+                    fraction * duration_seconds,
+                    &CameraPose {
+                        position,
+                        target: focus,
+                        y_fov_rad,
+                    },
+                )
+            })
+            .collect();
+        Self { keyframes }
+    }
+
+    pub fn time_range(&self) -> Option<(f32, f32)> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        Some((first.time, last.time))
+    }
+
+    pub fn push(&mut self, keyframe: CameraKeyframe) {
+        self.keyframes.push(keyframe);
+        self.keyframes
+            .sort_by(|left, right| left.time.total_cmp(&right.time));
+    }
+
+    pub fn sample(&self, time: f32) -> Option<CameraPose> {
+        let (start, end) = self.time_range()?;
+        let time = time.clamp(start, end);
+
+        let segment = self
+            .keyframes
+            .windows(2)
+            .position(|pair| time <= pair[1].time)
+            .unwrap_or(0);
+        let last = self.keyframes.len() - 1;
+        let keyframe = |index: usize| &self.keyframes[index.min(last)];
+
+        let k0 = keyframe(segment.saturating_sub(1));
+        let k1 = keyframe(segment);
+        let k2 = keyframe(segment + 1);
+        let k3 = keyframe(segment + 2);
+
+        let span = k2.time - k1.time;
+        let t = if span > 0.0 {
+            (time - k1.time) / span
+        } else {
+            0.0
+        };
+
+        let vector = |select: fn(&CameraKeyframe) -> [f32; 3]| {
+            catmull_rom(
+                Vec3::from(select(k0)),
+                Vec3::from(select(k1)),
+                Vec3::from(select(k2)),
+                Vec3::from(select(k3)),
+                t,
+            )
+        };
+        let fov_degrees = catmull_rom(
+            k0.fov_degrees,
+            k1.fov_degrees,
+            k2.fov_degrees,
+            k3.fov_degrees,
+            t,
+        );
+
+        Some(CameraPose {
+            position: vector(|keyframe| keyframe.position),
+            target: vector(|keyframe| keyframe.target),
+            y_fov_rad: fov_degrees.clamp(1.0, 179.0).to_radians(),
+        })
+    }
+}
+
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_camera_path(path: &std::path::Path) -> Result<CameraPath, Box<dyn std::error::Error>> {
+    let mut camera_path: CameraPath = serde_json::from_slice(&std::fs::read(path)?)?;
+    camera_path
+        .keyframes
+        .sort_by(|left, right| left.time.total_cmp(&right.time));
+    Ok(camera_path)
+}
+
+pub fn save_camera_path(
+    path: &std::path::Path,
+    camera_path: &CameraPath,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, serde_json::to_vec_pretty(camera_path)?)?;
+    Ok(())
+}
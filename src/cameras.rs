@@ -0,0 +1,151 @@
+use nightshade::prelude::*;
+use serde::Deserialize;
+
+const FRUSTUM_DEPTH_FRACTION: f32 = 0.1;
+const FRUSTUM_COLOR: [f32; 4] = [0.4, 0.6, 1.0, 1.0];
+
+#[derive(Clone, Deserialize)]
+pub struct TrainingCamera {
+    pub id: u32,
+    pub img_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub position: [f32; 3],
+    pub rotation: [[f32; 3]; 3],
+    pub fx: f32,
+    pub fy: f32,
+}
+
+impl TrainingCamera {
+    pub fn camera_to_world(&self, point: [f32; 3]) -> [f32; 3] {
+        let rotation = &self.rotation;
+        let mut world = self.position;
+        for (row, value) in world.iter_mut().enumerate() {
+            *value += rotation[row][0] * point[0]
+                + rotation[row][1] * point[1]
+                + rotation[row][2] * point[2];
+        }
+        world
+    }
+
+    pub fn y_fov_rad(&self) -> f32 {
+        2.0 * (self.height as f32 / (2.0 * self.fy)).atan()
+    }
+
+    pub fn frustum_corners(&self, depth: f32) -> [[f32; 3]; 4] {
+        let half_width = 0.5 * self.width as f32 / self.fx * depth;
+        let half_height = 0.5 * self.height as f32 / self.fy * depth;
+        [
+            [-half_width, -half_height, depth],
+            [half_width, -half_height, depth],
+            [half_width, half_height, depth],
+            [-half_width, half_height, depth],
+        ]
+        .map(|corner| self.camera_to_world(corner))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn sees(&self, point: [f32; 3]) -> bool {
+        let [x, y, z] = self.world_to_camera(point);
+        if z <= 0.0 {
+            return false;
+        }
+        let screen_x = x / z * self.fx + self.width as f32 * 0.5;
+        let screen_y = y / z * self.fy + self.height as f32 * 0.5;
+        (0.0..=self.width as f32).contains(&screen_x)
+            && (0.0..=self.height as f32).contains(&screen_y)
+    }
+
+    pub fn world_to_camera(&self, point: [f32; 3]) -> [f32; 3] {
+        let relative = [
+            point[0] - self.position[0],
+            point[1] - self.position[1],
+            point[2] - self.position[2],
+        ];
+        let rotation = &self.rotation;
+        [
+            rotation[0][0] * relative[0]
+                + rotation[1][0] * relative[1]
+                + rotation[2][0] * relative[2],
+            rotation[0][1] * relative[0]
+                + rotation[1][1] * relative[1]
+                + rotation[2][1] * relative[2],
+            rotation[0][2] * relative[0]
+                + rotation[1][2] * relative[1]
+                + rotation[2][2] * relative[2],
+        ]
+    }
+}
+
+pub fn frustum_lines(cameras: &[TrainingCamera], hidden: Option<usize>) -> Vec<Line> {
+    let count = cameras.len().max(1) as f32;
+    let centroid = cameras.iter().fold(Vec3::zeros(), |sum, camera| {
+        sum + Vec3::from(camera.position)
+    }) / count;
+    let spread = cameras
+        .iter()
+        .map(|camera| (Vec3::from(camera.position) - centroid).norm())
+        .sum::<f32>()
+        / count;
+    let depth = if spread > 0.0 {
+        spread * FRUSTUM_DEPTH_FRACTION
+    } else {
+        FRUSTUM_DEPTH_FRACTION
+    };
+
+    let color = Vec4::from(FRUSTUM_COLOR);
+    let mut lines = Vec::with_capacity(cameras.len() * 10);
+    for (index, camera) in cameras.iter().enumerate() {
+        if hidden == Some(index) {
+            continue;
+        }
+        let apex = Vec3::from(camera.position);
+        let corners = camera.frustum_corners(depth).map(Vec3::from);
+        let up_marker = Vec3::from(camera.camera_to_world([
+            0.0,
+            -0.5 * camera.height as f32 / camera.fy * depth * 1.4,
+            depth,
+        ]));
+
+        let mut push = |start: Vec3, end: Vec3| lines.push(Line { start, end, color });
+        for (corner_index, corner) in corners.iter().enumerate() {
+            push(apex, *corner);
+            push(*corner, corners[(corner_index + 1) % corners.len()]);
+        }
+        push(corners[0], up_marker);
+        push(corners[1], up_marker);
+    }
+    lines
+}
+
+pub fn load_cameras_json_from_bytes(data: &[u8]) -> Result<Vec<TrainingCamera>, serde_json::Error> {
+    serde_json::from_slice(data)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn find_cameras_json(ply_path: &std::path::Path) -> Option<std::path::PathBuf> {
+    ply_path
+        .ancestors()
+        .skip(1)
+        .take(4)
+        .map(|directory| directory.join("cameras.json"))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_cameras_json(path: &std::path::Path) -> Option<Vec<TrainingCamera>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", path.display(), error);
+            return None;
+        }
+    };
+    match load_cameras_json_from_bytes(&data) {
+        Ok(cameras) => Some(cameras),
+        Err(error) => {
+            eprintln!("Failed to parse {}: {}", path.display(), error);
+            None
+        }
+    }
+}
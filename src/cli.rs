@@ -0,0 +1,288 @@
+use crate::decimate;
+use crate::filter::{self, FilterSettings};
+use crate::merge::MergeSettings;
+use crate::ply;
+use crate::streaming;
+use nightshade::prelude::*;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: gaussian_splats <path_to.ply|.chunks> [--headless <output.png|exr>] \
+[--cpu-reference <output.png|exr>] [--camera-position x,y,z] [--camera-target x,y,z] \
+[--fov degrees] [--resolution WIDTHxHEIGHT] [--camera-path <path.json>] \
+[--frames <output_directory>] [--fps frames_per_second] [--object <path.ply>]... \
+[--merge <path.ply>]... [--merge-output <output.ply>] [--sh-degree 0-3] [--dedupe-distance distance] \
+[--filter <output.ply>] [--outlier-std-ratio ratio] [--outlier-neighbors k] \
+[--min-opacity opacity] [--max-scale scale] [--max-aspect-ratio ratio] \
+[--decimate <output.ply>] [--target-count count] \
+[--chunk <output.chunks>] [--chunk-size count] [--gpu-budget megabytes]";
+
+const DEFAULT_FRAMES_PER_SECOND: f32 = 30.0;
+const OFFLINE_WIDTH: u32 = 1280;
+const OFFLINE_HEIGHT: u32 = 720;
+
+pub struct CliArgs {
+    pub ply_path: PathBuf,
+    pub camera_position: Option<Vec3>,
+    pub camera_target: Option<Vec3>,
+    pub y_fov_rad: f32,
+    pub width: u32,
+    pub height: u32,
+    pub headless_path: Option<String>,
+    pub cpu_reference_path: Option<String>,
+    pub camera_path_file: Option<PathBuf>,
+    pub frames_directory: Option<PathBuf>,
+    pub frames_per_second: f32,
+    pub object_paths: Vec<PathBuf>,
+    pub merge_inputs: Vec<PathBuf>,
+    pub merge_output: Option<PathBuf>,
+    pub merge_settings: MergeSettings,
+    pub filter_path: Option<PathBuf>,
+    pub filter_settings: FilterSettings,
+    pub decimate_path: Option<PathBuf>,
+    pub decimate_target: usize,
+    pub chunk_path: Option<PathBuf>,
+    pub chunk_size: usize,
+    pub gpu_budget_mb: usize,
+}
+
+impl CliArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let ply_path = PathBuf::from(args.next().expect(USAGE));
+
+        let mut camera_position = None;
+        let mut camera_target = None;
+        let mut y_fov_rad = PerspectiveCamera::default().y_fov_rad;
+        let (mut width, mut height) = (OFFLINE_WIDTH, OFFLINE_HEIGHT);
+        let mut headless_path = None;
+        let mut cpu_reference_path = None;
+        let mut camera_path_file = None;
+        let mut frames_directory = None;
+        let mut frames_per_second = DEFAULT_FRAMES_PER_SECOND;
+        let mut object_paths = Vec::new();
+        let mut merge_inputs = Vec::new();
+        let mut merge_output = None;
+        let mut filter_path = None;
+        let mut decimate_path = None;
+        let mut decimate_target = decimate::DEFAULT_TARGET_COUNT;
+        let mut chunk_path = None;
+        let mut chunk_size = streaming::DEFAULT_CHUNK_SIZE;
+        let mut gpu_budget_mb = streaming::DEFAULT_GPU_BUDGET_MB;
+        let mut filter_settings = FilterSettings::default();
+        let mut merge_settings = MergeSettings {
+            sh_degree: None,
+            dedupe_distance: None,
+        };
+
+        while let Some(argument) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("{argument} requires a value\n{USAGE}"))
+            };
+            match argument.as_str() {
+                "--headless" => headless_path = Some(value()),
+                "--cpu-reference" => cpu_reference_path = Some(value()),
+                "--camera-position" => camera_position = Some(parse_vec3(&value())),
+                "--camera-target" => camera_target = Some(parse_vec3(&value())),
+                "--fov" => {
+                    let degrees: f32 = value().parse().expect("--fov expects degrees");
+                    y_fov_rad = degrees.to_radians();
+                }
+                "--resolution" => (width, height) = parse_resolution(&value()),
+                "--camera-path" => camera_path_file = Some(PathBuf::from(value())),
+                "--frames" => frames_directory = Some(PathBuf::from(value())),
+                "--fps" => {
+                    frames_per_second = value()
+                        .parse()
+                        .ok()
+                        .filter(|fps: &f32| *fps > 0.0)
+                        .expect("--fps expects a positive number");
+                }
+                "--object" => object_paths.push(PathBuf::from(value())),
+                "--merge" => merge_inputs.push(PathBuf::from(value())),
+                "--merge-output" => merge_output = Some(PathBuf::from(value())),
+                "--sh-degree" => {
+                    merge_settings.sh_degree = Some(
+                        value()
+                            .parse()
+                            .ok()
+                            .filter(|degree| *degree <= ply::MAX_SH_DEGREE)
+                            .expect("--sh-degree expects 0, 1, 2 or 3"),
+                    );
+                }
+                "--filter" => filter_path = Some(PathBuf::from(value())),
+                "--decimate" => decimate_path = Some(PathBuf::from(value())),
+                "--target-count" => {
+                    decimate_target = value()
+                        .parse()
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .expect("--target-count expects a positive count");
+                }
+                "--chunk" => chunk_path = Some(PathBuf::from(value())),
+                "--chunk-size" => {
+                    chunk_size = value()
+                        .parse()
+                        .ok()
+                        .filter(|size| *size > 0)
+                        .expect("--chunk-size expects a positive count");
+                }
+                "--gpu-budget" => {
+                    gpu_budget_mb = value()
+                        .parse()
+                        .ok()
+                        .filter(|megabytes| *megabytes > 0)
+                        .expect("--gpu-budget expects a positive number of megabytes");
+                }
+                "--outlier-std-ratio" => {
+                    filter_settings.outlier_std_ratio = Some(parse_positive(&argument, &value()));
+                }
+                "--outlier-neighbors" => {
+                    filter_settings.outlier_neighbors = value()
+                        .parse()
+                        .ok()
+                        .filter(|neighbors| (1..=filter::MAX_OUTLIER_NEIGHBORS).contains(neighbors))
+                        .expect("--outlier-neighbors expects a count from 1 to 32");
+                }
+                "--min-opacity" => {
+                    filter_settings.min_opacity = Some(parse_positive(&argument, &value()));
+                }
+                "--max-scale" => {
+                    filter_settings.max_scale = Some(parse_positive(&argument, &value()))
+                }
+                "--max-aspect-ratio" => {
+                    filter_settings.max_aspect_ratio = Some(parse_positive(&argument, &value()));
+                }
+                "--dedupe-distance" => {
+                    merge_settings.dedupe_distance = Some(
+                        value()
+                            .parse()
+                            .ok()
+                            .filter(|distance: &f32| *distance > 0.0)
+                            .expect("--dedupe-distance expects a positive number"),
+                    );
+                }
+                unknown => panic!("Unknown argument: {unknown}\n{USAGE}"),
+            }
+        }
+
+        if merge_output.is_some() && merge_inputs.is_empty() {
+            panic!("--merge-output requires at least one --merge input\n{USAGE}");
+        }
+
+        Self {
+            ply_path,
+            camera_position,
+            camera_target,
+            y_fov_rad,
+            width,
+            height,
+            headless_path,
+            cpu_reference_path,
+            camera_path_file,
+            frames_directory,
+            frames_per_second,
+            object_paths,
+            merge_inputs,
+            merge_output,
+            merge_settings,
+            filter_path,
+            filter_settings,
+            decimate_path,
+            decimate_target,
+            chunk_path,
+            chunk_size,
+            gpu_budget_mb,
+        }
+    }
+}
+
+fn parse_positive(argument: &str, value: &str) -> f32 {
+    value
+        .parse()
+        .ok()
+        .filter(|value: &f32| *value > 0.0)
+        .unwrap_or_else(|| panic!("{argument} expects a positive number"))
+}
+
+fn parse_vec3(value: &str) -> Vec3 {
+    let components: Vec<f32> = value
+        .split(',')
+        .map(|component| {
+            component
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid vector component in {value}"))
+        })
+        .collect();
+    match components.as_slice() {
+        [x, y, z] => Vec3::new(*x, *y, *z),
+        _ => panic!("Expected x,y,z but got {value}"),
+    }
+}
+
+fn parse_resolution(value: &str) -> (u32, u32) {
+    value
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .unwrap_or_else(|| panic!("Expected WIDTHxHEIGHT but got {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> CliArgs {
+        CliArgs::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn defaults_apply_without_options() {
+        let args = parse("scene.ply");
+        assert_eq!(args.ply_path, PathBuf::from("scene.ply"));
+        assert_eq!((args.width, args.height), (OFFLINE_WIDTH, OFFLINE_HEIGHT));
+        assert_eq!(args.frames_per_second, DEFAULT_FRAMES_PER_SECOND);
+        assert!(args.merge_inputs.is_empty() && args.object_paths.is_empty());
+        assert!(args.filter_settings == FilterSettings::default());
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let args = parse(
+            "room.ply --object car.ply --merge a.ply --merge b.ply --merge-output out.ply \
+             --sh-degree 2 --dedupe-distance 0.01 --camera-position 1,2,3 --fov 90 \
+             --resolution 640x480 --headless render.png --min-opacity 0.1",
+        );
+        assert_eq!(args.object_paths, [PathBuf::from("car.ply")]);
+        assert_eq!(
+            args.merge_inputs,
+            [PathBuf::from("a.ply"), PathBuf::from("b.ply")]
+        );
+        assert_eq!(args.merge_output, Some(PathBuf::from("out.ply")));
+        assert_eq!(args.merge_settings.sh_degree, Some(2));
+        assert_eq!(args.merge_settings.dedupe_distance, Some(0.01));
+        assert_eq!(args.camera_position, Some(Vec3::new(1.0, 2.0, 3.0)));
+        assert!((args.y_fov_rad - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        assert_eq!((args.width, args.height), (640, 480));
+        assert_eq!(args.headless_path.as_deref(), Some("render.png"));
+        assert_eq!(args.filter_settings.min_opacity, Some(0.1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown argument: --bogus")]
+    fn unknown_arguments_panic() {
+        parse("scene.ply --bogus");
+    }
+
+    #[test]
+    #[should_panic(expected = "--merge-output requires at least one --merge input")]
+    fn merge_output_needs_inputs() {
+        parse("scene.ply --merge-output out.ply");
+    }
+
+    #[test]
+    #[should_panic(expected = "--sh-degree expects 0, 1, 2 or 3")]
+    fn sh_degree_is_bounded() {
+        parse("scene.ply --merge a.ply --sh-degree 4");
+    }
+}
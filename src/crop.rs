@@ -0,0 +1,478 @@
+use crate::bounds::SceneBounds;
+use crate::scene_transform::SceneTransform;
+use nightshade::prelude::*;
+
+pub const MAX_CROP_VOLUMES: usize = 16;
+pub const HANDLE_PICK_RADIUS_PIXELS: f32 = 12.0;
+
+const MIN_EXTENT: f32 = 1e-3;
+const SPHERE_SEGMENTS: usize = 48;
+const HANDLE_SIZE_FRACTION: f32 = 0.06;
+const KEEP_COLOR: Vec4 = Vec4::new(0.3, 0.9, 0.4, 1.0);
+const REMOVE_COLOR: Vec4 = Vec4::new(0.95, 0.3, 0.25, 1.0);
+const DISABLED_COLOR: Vec4 = Vec4::new(0.5, 0.5, 0.5, 1.0);
+const SELECTED_COLOR: Vec4 = Vec4::new(1.0, 0.85, 0.2, 1.0);
+const HANDLE_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 1.0);
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CropShape {
+    Aabb,
+    Obb,
+    Sphere,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CropMode {
+    Keep,
+    Remove,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct CropVolume {
+    pub shape: CropShape,
+    pub mode: CropMode,
+    pub enabled: bool,
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    pub rotation_degrees: Vec3,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuCropVolume {
+    pub center: [f32; 3],
+    pub shape: u32,
+    pub half_extents: [f32; 3],
+    pub mode: u32,
+    pub rotation: [f32; 4],
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CropHandle {
+    Center,
+    Face { axis: usize, sign: f32 },
+}
+
+#[derive(Copy, Clone)]
+pub struct CropDrag {
+    pub volume: usize,
+    pub handle: CropHandle,
+    pub grab_offset: Vec3,
+}
+
+impl CropVolume {
+    pub fn new(shape: CropShape, bounds: Option<&SceneBounds>) -> Self {
+        let (center, half_extents) = bounds.map_or((Vec3::zeros(), Vec3::repeat(1.0)), |bounds| {
+            (bounds.center(), (bounds.max - bounds.min) * 0.5)
+        });
+        let half_extents = match shape {
+            CropShape::Sphere => Vec3::repeat(half_extents.max()),
+            CropShape::Aabb | CropShape::Obb => half_extents,
+        };
+        Self {
+            shape,
+            mode: CropMode::Keep,
+            enabled: true,
+            center,
+            half_extents: half_extents.map(|extent| extent.max(MIN_EXTENT)),
+            rotation_degrees: Vec3::zeros(),
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        if self.shape != CropShape::Obb {
+            return Quat::identity();
+        }
+        let radians = self.rotation_degrees.map(f32::to_radians);
+        nalgebra_glm::quat_angle_axis(radians.z, &Vec3::z())
+            * nalgebra_glm::quat_angle_axis(radians.y, &Vec3::y())
+            * nalgebra_glm::quat_angle_axis(radians.x, &Vec3::x())
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.half_extents.x
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        let local = nalgebra_glm::quat_rotate_vec3(
+            &nalgebra_glm::quat_conjugate(&self.rotation()),
+            &(point - self.center),
+        );
+        match self.shape {
+            CropShape::Sphere => local.norm_squared() <= self.radius() * self.radius(),
+            CropShape::Aabb | CropShape::Obb => {
+                (0..3).all(|axis| local[axis].abs() <= self.half_extents[axis])
+            }
+        }
+    }
+
+    pub fn to_gpu(self, transform: &SceneTransform) -> GpuCropVolume {
+        GpuCropVolume {
+            center: transform.transform_point(self.center).into(),
+            shape: self.shape as u32,
+            half_extents: (self.half_extents * transform.scale).into(),
+            mode: self.mode as u32,
+            rotation: (transform.rotation_quat() * self.rotation()).coords.into(),
+        }
+    }
+
+    pub fn axis(&self, axis: usize) -> Vec3 {
+        let mut unit = Vec3::zeros();
+        unit[axis] = 1.0;
+        nalgebra_glm::quat_rotate_vec3(&self.rotation(), &unit)
+    }
+
+    pub fn extent(&self, axis: usize) -> f32 {
+        match self.shape {
+            CropShape::Sphere => self.radius(),
+            CropShape::Aabb | CropShape::Obb => self.half_extents[axis],
+        }
+    }
+
+    pub fn handle_position(&self, handle: CropHandle) -> Vec3 {
+        match handle {
+            CropHandle::Center => self.center,
+            CropHandle::Face { axis, sign } => {
+                self.center + self.axis(axis) * sign * self.extent(axis)
+            }
+        }
+    }
+
+    pub fn handles(&self) -> Vec<CropHandle> {
+        let mut handles = vec![CropHandle::Center];
+        for axis in 0..3 {
+            for sign in [1.0, -1.0] {
+                handles.push(CropHandle::Face { axis, sign });
+            }
+        }
+        handles
+    }
+
+    fn set_extent(&mut self, axis: usize, extent: f32) {
+        let extent = extent.max(MIN_EXTENT);
+        match self.shape {
+            CropShape::Sphere => self.half_extents = Vec3::repeat(extent),
+            CropShape::Aabb | CropShape::Obb => self.half_extents[axis] = extent,
+        }
+    }
+
+    fn outline(&self, color: Vec4, lines: &mut Vec<Line>) {
+        let rotation = self.rotation();
+        let to_world =
+            |local: Vec3| self.center + nalgebra_glm::quat_rotate_vec3(&rotation, &local);
+        match self.shape {
+            CropShape::Sphere => {
+                for axis in 0..3 {
+                    let first = self.axis((axis + 1) % 3) * self.radius();
+                    let second = self.axis((axis + 2) % 3) * self.radius();
+                    let point = |segment: usize| {
+                        let angle = segment as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+                        self.center + first * angle.cos() + second * angle.sin()
+                    };
+                    for segment in 0..SPHERE_SEGMENTS {
+                        lines.push(Line {
+                            start: point(segment),
+                            end: point(segment + 1),
+                            color,
+                        });
+                    }
+                }
+            }
+            CropShape::Aabb | CropShape::Obb => {
+                let corner = |index: usize| {
+                    to_world(
+                        Vec3::new(
+                            if index & 1 == 0 { -1.0 } else { 1.0 },
+                            if index & 2 == 0 { -1.0 } else { 1.0 },
+                            if index & 4 == 0 { -1.0 } else { 1.0 },
+                        )
+                        .component_mul(&self.half_extents),
+                    )
+                };
+                for index in 0..8 {
+                    for bit in [1, 2, 4] {
+                        if index & bit == 0 {
+                            lines.push(Line {
+                                start: corner(index),
+                                end: corner(index | bit),
+                                color,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn keeps(volumes: &[CropVolume], point: Vec3) -> bool {
+    let mut has_keep = false;
+    let mut inside_keep = false;
+    for volume in volumes.iter().filter(|volume| volume.enabled) {
+        let inside = volume.contains(point);
+        match volume.mode {
+            CropMode::Remove if inside => return false,
+            CropMode::Remove => {}
+            CropMode::Keep => {
+                has_keep = true;
+                inside_keep |= inside;
+            }
+        }
+    }
+    !has_keep || inside_keep
+}
+
+pub fn gpu_volumes(volumes: &[CropVolume], transform: &SceneTransform) -> Vec<GpuCropVolume> {
+    volumes
+        .iter()
+        .filter(|volume| volume.enabled)
+        .take(MAX_CROP_VOLUMES)
+        .map(|volume| volume.to_gpu(transform))
+        .collect()
+}
+
+pub fn crop_lines(volumes: &[CropVolume], selected: Option<usize>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for (index, volume) in volumes.iter().enumerate() {
+        let color = if selected == Some(index) {
+            SELECTED_COLOR
+        } else if !volume.enabled {
+            DISABLED_COLOR
+        } else if volume.mode == CropMode::Remove {
+            REMOVE_COLOR
+        } else {
+            KEEP_COLOR
+        };
+        volume.outline(color, &mut lines);
+
+        let handle_size = volume.half_extents.max() * HANDLE_SIZE_FRACTION;
+        for handle in volume.handles() {
+            let position = volume.handle_position(handle);
+            for axis in 0..3 {
+                let offset = volume.axis(axis) * handle_size;
+                lines.push(Line {
+                    start: position - offset,
+                    end: position + offset,
+                    color: HANDLE_COLOR,
+                });
+            }
+        }
+    }
+    lines
+}
+
+pub fn pick_handle(
+    volumes: &[CropVolume],
+    project: impl Fn(Vec3) -> Option<Vec2>,
+    cursor: Vec2,
+) -> Option<(usize, CropHandle)> {
+    volumes
+        .iter()
+        .enumerate()
+        .flat_map(|(index, volume)| {
+            volume
+                .handles()
+                .into_iter()
+                .map(move |handle| (index, handle, volume.handle_position(handle)))
+        })
+        .filter_map(|(index, handle, position)| {
+            let distance = (project(position)? - cursor).norm();
+            (distance <= HANDLE_PICK_RADIUS_PIXELS).then_some((index, handle, distance))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(index, handle, _)| (index, handle))
+}
+
+pub fn begin_drag(
+    volume: &CropVolume,
+    index: usize,
+    handle: CropHandle,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+    view_direction: Vec3,
+) -> CropDrag {
+    let grab_offset = match handle {
+        CropHandle::Center => {
+            intersect_plane(ray_origin, ray_direction, volume.center, view_direction)
+                .map_or(Vec3::zeros(), |hit| volume.center - hit)
+        }
+        CropHandle::Face { .. } => Vec3::zeros(),
+    };
+    CropDrag {
+        volume: index,
+        handle,
+        grab_offset,
+    }
+}
+
+pub fn apply_drag(
+    drag: &CropDrag,
+    volume: &mut CropVolume,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+    view_direction: Vec3,
+) {
+    match drag.handle {
+        CropHandle::Center => {
+            if let Some(hit) =
+                intersect_plane(ray_origin, ray_direction, volume.center, view_direction)
+            {
+                volume.center = hit + drag.grab_offset;
+            }
+        }
+        CropHandle::Face { axis, sign } => {
+            let direction = volume.axis(axis);
+            if let Some(distance) =
+                closest_line_parameter(volume.center, direction, ray_origin, ray_direction)
+            {
+                volume.set_extent(axis, distance * sign);
+            }
+        }
+    }
+}
+
+pub fn intersect_plane(origin: Vec3, direction: Vec3, point: Vec3, normal: Vec3) -> Option<Vec3> {
+    let denominator = direction.dot(&normal);
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    let t = (point - origin).dot(&normal) / denominator;
+    (t > 0.0).then(|| origin + direction * t)
+}
+
+pub fn closest_line_parameter(
+    line_origin: Vec3,
+    line_direction: Vec3,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+) -> Option<f32> {
+    let offset = line_origin - ray_origin;
+    let a = line_direction.dot(&line_direction);
+    let b = line_direction.dot(&ray_direction);
+    let c = ray_direction.dot(&ray_direction);
+    let d = line_direction.dot(&offset);
+    let e = ray_direction.dot(&offset);
+    let denominator = a * c - b * b;
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    Some((b * e - c * d) / denominator)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn cropped_path(ply_path: &std::path::Path) -> std::path::PathBuf {
+    ply_path.with_extension("cropped.ply")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(shape: CropShape, mode: CropMode, center: Vec3, half_extents: Vec3) -> CropVolume {
+        CropVolume {
+            shape,
+            mode,
+            enabled: true,
+            center,
+            half_extents,
+            rotation_degrees: Vec3::zeros(),
+        }
+    }
+
+    #[test]
+    fn shapes_contain_their_interior() {
+        let aabb = volume(
+            CropShape::Aabb,
+            CropMode::Keep,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.5, 0.25),
+        );
+        assert!(aabb.contains(Vec3::new(1.9, 0.4, -0.2)));
+        assert!(!aabb.contains(Vec3::new(1.0, 0.6, 0.0)));
+        assert!(!aabb.contains(Vec3::new(1.0, 0.0, 0.3)));
+
+        let sphere = volume(
+            CropShape::Sphere,
+            CropMode::Keep,
+            Vec3::zeros(),
+            Vec3::repeat(1.0),
+        );
+        assert!(sphere.contains(Vec3::new(0.7, 0.7, 0.0)));
+        assert!(!sphere.contains(Vec3::new(0.8, 0.8, 0.0)));
+    }
+
+    #[test]
+    fn obb_rotation_turns_the_box() {
+        let mut obb = volume(
+            CropShape::Obb,
+            CropMode::Keep,
+            Vec3::zeros(),
+            Vec3::new(2.0, 0.2, 0.2),
+        );
+        assert!(obb.contains(Vec3::new(1.8, 0.0, 0.0)));
+        assert!(!obb.contains(Vec3::new(0.0, 1.8, 0.0)));
+
+        obb.rotation_degrees = Vec3::new(0.0, 0.0, 90.0);
+        assert!(!obb.contains(Vec3::new(1.8, 0.0, 0.0)));
+        assert!(obb.contains(Vec3::new(0.0, 1.8, 0.0)));
+
+        obb.rotation_degrees = Vec3::new(0.0, 0.0, 45.0);
+        let diagonal = Vec3::new(1.0, 1.0, 0.0).normalize() * 1.8;
+        assert!(obb.contains(diagonal));
+        assert!(!obb.contains(Vec3::new(1.8, 0.0, 0.0)));
+
+        let mut aabb = obb;
+        aabb.shape = CropShape::Aabb;
+        assert!(aabb.contains(Vec3::new(1.8, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn remove_takes_precedence_over_keep() {
+        let keep = volume(
+            CropShape::Aabb,
+            CropMode::Keep,
+            Vec3::zeros(),
+            Vec3::repeat(1.0),
+        );
+        let remove = volume(
+            CropShape::Sphere,
+            CropMode::Remove,
+            Vec3::zeros(),
+            Vec3::repeat(0.5),
+        );
+        let volumes = [keep, remove];
+        assert!(!keeps(&volumes, Vec3::zeros()));
+        assert!(keeps(&volumes, Vec3::new(0.8, 0.0, 0.0)));
+        assert!(!keeps(&volumes, Vec3::new(2.0, 0.0, 0.0)));
+
+        assert!(keeps(&[remove], Vec3::new(2.0, 0.0, 0.0)));
+        assert!(!keeps(&[remove], Vec3::zeros()));
+        assert!(keeps(&[], Vec3::zeros()));
+    }
+
+    #[test]
+    fn any_keep_volume_keeps_and_disabled_volumes_are_ignored() {
+        let left = volume(
+            CropShape::Aabb,
+            CropMode::Keep,
+            Vec3::new(-2.0, 0.0, 0.0),
+            Vec3::repeat(0.5),
+        );
+        let right = volume(
+            CropShape::Aabb,
+            CropMode::Keep,
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::repeat(0.5),
+        );
+        assert!(keeps(&[left, right], Vec3::new(-2.0, 0.0, 0.0)));
+        assert!(keeps(&[left, right], Vec3::new(2.0, 0.0, 0.0)));
+        assert!(!keeps(&[left, right], Vec3::zeros()));
+
+        let disabled = CropVolume {
+            enabled: false,
+            ..right
+        };
+        assert!(keeps(&[disabled], Vec3::zeros()));
+        assert!(!keeps(&[left, disabled], Vec3::new(2.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::bounds::SceneBounds;
+use crate::cameras::TrainingCamera;
+use crate::gaussian::{GaussianMoments, GpuGaussian, RawGaussian};
+use nightshade::prelude::*;
+use std::collections::HashMap;
+
+pub const DEFAULT_TARGET_COUNT: usize = 500_000;
+
+const SH_C0: f32 = 0.282_094_8;
+const MAX_COLOR_DIFFERENCE: f32 = 0.1;
+const MERGE_DISTANCE_SCALES: f32 = 3.0;
+const POINTS_PER_CELL: f32 = 4.0;
+const MAX_TOLERANCE: f32 = 16.0;
+
+struct Splat {
+    gaussian: RawGaussian,
+    visibility: f32,
+}
+
+impl Splat {
+    fn opacity(&self) -> f32 {
+        1.0 / (1.0 + (-self.gaussian.opacity).exp())
+    }
+
+    fn volume(&self) -> f32 {
+        self.gaussian.scale.iter().sum::<f32>().exp()
+    }
+
+    fn mass(&self) -> f32 {
+        self.opacity() * self.volume()
+    }
+
+    fn importance(&self) -> f32 {
+        self.mass() * self.visibility
+    }
+
+    fn extent(&self) -> f32 {
+        Vec3::from(self.gaussian.scale).max().exp()
+    }
+
+    fn moments(&self) -> GaussianMoments {
+        GaussianMoments::from(&GpuGaussian::from(&self.gaussian))
+    }
+}
+
+pub fn decimate(
+    gaussians: Vec<RawGaussian>,
+    cameras: &[TrainingCamera],
+    target: usize,
+) -> Vec<RawGaussian> {
+    let mut splats: Vec<Splat> = gaussians
+        .into_iter()
+        .map(|gaussian| Splat {
+            visibility: if cameras.is_empty() {
+                1.0
+            } else {
+                cameras
+                    .iter()
+                    .filter(|camera| camera.sees(gaussian.position))
+                    .count() as f32
+                    / cameras.len() as f32
+            },
+            gaussian,
+        })
+        .collect();
+
+    let mut tolerance = 1.0;
+    while splats.len() > target && tolerance <= MAX_TOLERANCE {
+        let needed = splats.len() - target;
+        let merged;
+        (splats, merged) = merge_pass(splats, needed, tolerance);
+        if merged * 2 < needed {
+            tolerance *= 2.0;
+        }
+    }
+
+    if splats.len() > target {
+        splats.sort_by(|a, b| b.importance().total_cmp(&a.importance()));
+        splats.truncate(target);
+    }
+    splats.into_iter().map(|splat| splat.gaussian).collect()
+}
+
+fn merge_pass(splats: Vec<Splat>, needed: usize, tolerance: f32) -> (Vec<Splat>, usize) {
+    let positions: Vec<GpuGaussian> = splats
+        .iter()
+        .map(|splat| GpuGaussian::from(&splat.gaussian))
+        .collect();
+    let cell_size = SceneBounds::from_gaussians(&positions).map_or(1.0, |bounds| {
+        let extent = (bounds.max - bounds.min).sup(&Vec3::repeat(1e-6));
+        (extent.x * extent.y * extent.z * POINTS_PER_CELL / splats.len() as f32)
+            .cbrt()
+            .max(1e-6)
+    });
+    let cell = |splat: &Splat| {
+        let cell =
+            Vec3::from(splat.gaussian.position).map(|value| (value / cell_size).floor() as i32);
+        [cell.x, cell.y, cell.z]
+    };
+    let mut grid: HashMap<[i32; 3], Vec<u32>> = HashMap::new();
+    for (index, splat) in splats.iter().enumerate() {
+        grid.entry(cell(splat)).or_default().push(index as u32);
+    }
+
+    let mut order: Vec<usize> = (0..splats.len()).collect();
+    order.sort_by(|a, b| splats[*a].importance().total_cmp(&splats[*b].importance()));
+
+    let mut partner: Vec<Option<usize>> = vec![None; splats.len()];
+    let mut pairs = 0;
+    for &index in &order {
+        if pairs == needed {
+            break;
+        }
+        if partner[index].is_some() {
+            continue;
+        }
+        let splat = &splats[index];
+        let position = Vec3::from(splat.gaussian.position);
+        let center = cell(splat);
+        let mut best: Option<(usize, f32)> = None;
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(candidates) = grid.get(&[center[0] + x, center[1] + y, center[2] + z])
+                    else {
+                        continue;
+                    };
+                    for &candidate in candidates {
+                        let candidate = candidate as usize;
+                        if candidate == index || partner[candidate].is_some() {
+                            continue;
+                        }
+                        let other = &splats[candidate];
+                        let color_difference = (Vec3::from(splat.gaussian.sh_dc)
+                            - Vec3::from(other.gaussian.sh_dc))
+                        .norm()
+                            * SH_C0;
+                        if color_difference > MAX_COLOR_DIFFERENCE * tolerance {
+                            continue;
+                        }
+                        let distance = (Vec3::from(other.gaussian.position) - position).norm();
+                        let reach =
+                            MERGE_DISTANCE_SCALES * tolerance * (splat.extent() + other.extent());
+                        if distance <= reach && best.is_none_or(|(_, best)| distance < best) {
+                            best = Some((candidate, distance));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some((candidate, _)) = best {
+            partner[index] = Some(candidate);
+            partner[candidate] = Some(index);
+            pairs += 1;
+        }
+    }
+
+    let mut splats: Vec<Option<Splat>> = splats.into_iter().map(Some).collect();
+    let mut result = Vec::with_capacity(splats.len() - pairs);
+    for index in 0..splats.len() {
+        let Some(splat) = splats[index].take() else {
+            continue;
+        };
+        match partner[index] {
+            Some(other) => {
+                let other = splats[other]
+                    .take()
+                    .expect("merge partners are taken together");
+                result.push(moment_match(&splat, &other));
+            }
+            None => result.push(splat),
+        }
+    }
+    (result, pairs)
+}
+
+fn moment_match(a: &Splat, b: &Splat) -> Splat {
+    let mut moments = a.moments();
+    moments.merge(&b.moments());
+    let fitted = moments.fit();
+    let weight_a = a.moments().mass() / moments.mass();
+    let weight_b = 1.0 - weight_a;
+
+    let blend = |a: &[f32], b: &[f32], out: &mut [f32]| {
+        for ((out, a), b) in out.iter_mut().zip(a).zip(b) {
+            *out = a * weight_a + b * weight_b;
+        }
+    };
+    let mut gaussian = a.gaussian;
+    gaussian.position = fitted.position;
+    gaussian.normals = [0.0; 3];
+    blend(&a.gaussian.sh_dc, &b.gaussian.sh_dc, &mut gaussian.sh_dc);
+    blend(
+        &a.gaussian.sh_rest,
+        &b.gaussian.sh_rest,
+        &mut gaussian.sh_rest,
+    );
+    gaussian.opacity = fitted.opacity_logit;
+    gaussian.scale = fitted.scale_log;
+    gaussian.rotation = fitted.rotation;
+    Splat {
+        gaussian,
+        visibility: a.visibility * weight_a + b.visibility * weight_b,
+    }
+}
+
+pub fn decimated_path(ply_path: &std::path::Path) -> std::path::PathBuf {
+    ply_path.with_extension("decimated.ply")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sh_rotation::{SH_COEFFICIENTS_PER_CHANNEL, sh_basis};
+    use nightshade::prelude::nalgebra_glm::{DMat3, DVec3};
+
+    fn raw_gaussian(position: [f32; 3], opacity: f32, scale: [f32; 3]) -> RawGaussian {
+        RawGaussian {
+            position,
+            opacity,
+            scale,
+            rotation: [1.0, 0.0, 0.0, 0.0],
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    fn splat(gaussian: RawGaussian) -> Splat {
+        Splat {
+            gaussian,
+            visibility: 1.0,
+        }
+    }
+
+    fn covariance(gaussian: &RawGaussian) -> DMat3 {
+        let [w, x, y, z] = gaussian.rotation;
+        let rotation =
+            nalgebra_glm::quat_to_mat3(&nalgebra_glm::quat_normalize(&Quat::new(w, x, y, z)))
+                .cast::<f64>();
+        let variances = Vec3::from(gaussian.scale).map(|scale| (2.0 * scale).exp());
+        rotation * DMat3::from_diagonal(&variances.cast::<f64>()) * rotation.transpose()
+    }
+
+    fn radiance(gaussian: &RawGaussian, direction: &Vec3) -> f32 {
+        let basis = sh_basis(direction);
+        (0..SH_COEFFICIENTS_PER_CHANNEL)
+            .map(|coefficient| gaussian.sh_rest[coefficient] * basis[coefficient])
+            .sum()
+    }
+
+    #[test]
+    fn merged_pair_keeps_the_mixture_moments() {
+        let mut a = raw_gaussian([0.0, 0.1, 0.0], 1.5, [-2.0, -2.5, -3.0]);
+        a.rotation = [(0.3_f32).cos(), (0.3_f32).sin(), 0.0, 0.0];
+        let mut b = raw_gaussian([0.2, -0.1, 0.05], -0.5, [-2.2, -2.2, -2.8]);
+        b.rotation = [(0.7_f32).cos(), 0.0, 0.0, (0.7_f32).sin()];
+        let (a, b) = (splat(a), splat(b));
+
+        let masses = [a.mass() as f64, b.mass() as f64];
+        let total = masses[0] + masses[1];
+        let mean = [&a, &b]
+            .iter()
+            .zip(masses)
+            .map(|(splat, mass)| Vec3::from(splat.gaussian.position).cast::<f64>() * mass)
+            .sum::<DVec3>()
+            / total;
+        let second = [&a, &b]
+            .iter()
+            .zip(masses)
+            .map(|(splat, mass)| {
+                let position = Vec3::from(splat.gaussian.position).cast::<f64>();
+                (covariance(&splat.gaussian) + position * position.transpose()) * mass
+            })
+            .sum::<DMat3>()
+            / total;
+        let expected_covariance = second - mean * mean.transpose();
+
+        let merged = moment_match(&a, &b);
+        let merged_mean = Vec3::from(merged.gaussian.position).cast::<f64>();
+        assert!((merged_mean - mean).norm() < 1e-5);
+        assert!(
+            (covariance(&merged.gaussian) - expected_covariance).norm()
+                < 1e-4 * expected_covariance.norm()
+        );
+    }
+
+    #[test]
+    fn merged_sh_is_the_mass_weighted_world_radiance() {
+        let mut a = raw_gaussian([0.0; 3], 1.0, [-2.0, -2.5, -3.0]);
+        a.rotation = [(0.4_f32).cos(), 0.0, (0.4_f32).sin(), 0.0];
+        let mut b = raw_gaussian([0.05, 0.0, 0.0], 0.0, [-2.4, -2.0, -2.6]);
+        b.rotation = [(1.1_f32).cos(), (1.1_f32).sin(), 0.0, 0.0];
+        for coefficient in 0..SH_COEFFICIENTS_PER_CHANNEL {
+            a.sh_rest[coefficient] = (coefficient as f32 * 0.37).sin();
+            b.sh_rest[coefficient] = (coefficient as f32 * 0.91).cos();
+        }
+        let (a, b) = (splat(a), splat(b));
+        let weight_a = a.mass() / (a.mass() + b.mass());
+        let merged = moment_match(&a, &b);
+
+        for direction in [
+            Vec3::x(),
+            Vec3::new(0.3, -0.8, 0.5).normalize(),
+            Vec3::new(-0.6, 0.2, 0.77).normalize(),
+        ] {
+            let expected = radiance(&a.gaussian, &direction) * weight_a
+                + radiance(&b.gaussian, &direction) * (1.0 - weight_a);
+            assert!((radiance(&merged.gaussian, &direction) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn decimate_reaches_the_target_count() {
+        let gaussians: Vec<RawGaussian> = (0..1000)
+            .map(|index| {
+                let position = [
+                    (index % 10) as f32 * 0.01,
+                    (index / 10 % 10) as f32 * 0.01,
+                    (index / 100) as f32 * 0.01,
+                ];
+                raw_gaussian(position, 0.0, [-5.0; 3])
+            })
+            .collect();
+        for target in [999, 500, 137, 1] {
+            assert_eq!(decimate(gaussians.clone(), &[], target).len(), target);
+        }
+        assert_eq!(decimate(gaussians.clone(), &[], 2000).len(), 1000);
+    }
+
+    #[test]
+    fn unmergeable_gaussians_keep_the_most_important() {
+        let gaussians: Vec<RawGaussian> = (0..8)
+            .map(|index| {
+                let mut gaussian = raw_gaussian(
+                    [index as f32 * 100.0, 0.0, 0.0],
+                    index as f32 - 4.0,
+                    [-3.0; 3],
+                );
+                gaussian.sh_dc = [if index % 2 == 0 { 20.0 } else { -20.0 }, 0.0, 0.0];
+                gaussian
+            })
+            .collect();
+        let kept = decimate(gaussians, &[], 3);
+        let mut positions: Vec<f32> = kept.iter().map(|gaussian| gaussian.position[0]).collect();
+        positions.sort_by(f32::total_cmp);
+        assert_eq!(positions, [500.0, 600.0, 700.0]);
+    }
+}
@@ -0,0 +1,402 @@
+use crate::gaussian::{GpuGaussian, SplatTransform};
+#[cfg(not(target_arch = "wasm32"))]
+use nightshade::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GaussianState {
+    Visible,
+    Hidden,
+    Deleted,
+}
+
+enum EditChange {
+    State {
+        before: Vec<GaussianState>,
+        after: GaussianState,
+    },
+    Transform(SplatTransform),
+}
+
+struct EditOperation {
+    label: &'static str,
+    indices: Vec<u32>,
+    change: EditChange,
+}
+
+pub struct Edits {
+    states: Vec<GaussianState>,
+    inactive: Vec<u32>,
+    filtered: Vec<u32>,
+    filtered_count: usize,
+    hidden_count: usize,
+    deleted_count: usize,
+    undo_stack: Vec<EditOperation>,
+    redo_stack: Vec<EditOperation>,
+    dirty: bool,
+}
+
+impl Edits {
+    pub fn new(len: usize) -> Self {
+        Self {
+            states: vec![GaussianState::Visible; len],
+            inactive: vec![0; len.div_ceil(32).max(1)],
+            filtered: vec![0; len.div_ceil(32).max(1)],
+            filtered_count: 0,
+            hidden_count: 0,
+            deleted_count: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    pub fn state(&self, index: usize) -> GaussianState {
+        self.states[index]
+    }
+
+    pub fn is_active(&self, index: usize) -> bool {
+        self.state(index) == GaussianState::Visible && !self.is_filtered(index)
+    }
+
+    pub fn is_filtered(&self, index: usize) -> bool {
+        self.filtered[index / 32] & (1 << (index % 32)) != 0
+    }
+
+    pub fn filtered_count(&self) -> usize {
+        self.filtered_count
+    }
+
+    pub fn filtered(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.states.len()).filter(|index| self.is_filtered(*index))
+    }
+
+    pub fn set_filtered(&mut self, rejected: &[bool]) {
+        self.filtered.fill(0);
+        self.filtered_count = 0;
+        for (index, _) in rejected
+            .iter()
+            .enumerate()
+            .filter(|(_, rejected)| **rejected)
+        {
+            self.filtered[index / 32] |= 1 << (index % 32);
+            self.filtered_count += 1;
+        }
+        self.dirty = true;
+    }
+
+    pub fn hidden_count(&self) -> usize {
+        self.hidden_count
+    }
+
+    pub fn deleted_count(&self) -> usize {
+        self.deleted_count
+    }
+
+    pub fn hidden(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.states.len()).filter(|index| self.state(*index) == GaussianState::Hidden)
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.states.len()).filter(|index| self.is_active(*index))
+    }
+
+    pub fn apply(
+        &mut self,
+        label: &'static str,
+        indices: impl Iterator<Item = usize>,
+        state: GaussianState,
+    ) -> bool {
+        let indices: Vec<u32> = indices
+            .filter(|index| {
+                let current = self.states[*index];
+                current != state && current != GaussianState::Deleted
+            })
+            .map(|index| index as u32)
+            .collect();
+        if indices.is_empty() {
+            return false;
+        }
+        let before = indices
+            .iter()
+            .map(|index| self.states[*index as usize])
+            .collect();
+        for index in &indices {
+            self.set_state(*index as usize, state);
+        }
+        self.undo_stack.push(EditOperation {
+            label,
+            indices,
+            change: EditChange::State {
+                before,
+                after: state,
+            },
+        });
+        self.redo_stack.clear();
+        true
+    }
+
+    pub fn push_transform(
+        &mut self,
+        label: &'static str,
+        indices: Vec<u32>,
+        transform: SplatTransform,
+    ) {
+        if indices.is_empty() {
+            return;
+        }
+        self.undo_stack.push(EditOperation {
+            label,
+            indices,
+            change: EditChange::Transform(transform),
+        });
+        self.redo_stack.clear();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn rotation_groups(&self) -> (Vec<Quat>, Vec<u32>) {
+        let mut rotations = vec![Quat::identity()];
+        let mut groups = vec![0u32; self.states.len()];
+        for operation in &self.undo_stack {
+            let EditChange::Transform(transform) = &operation.change else {
+                continue;
+            };
+            if transform.rotation == Quat::identity() {
+                continue;
+            }
+            let mut composed: HashMap<u32, u32> = HashMap::new();
+            for index in &operation.indices {
+                let group = &mut groups[*index as usize];
+                *group = *composed.entry(*group).or_insert_with(|| {
+                    rotations.push(nalgebra_glm::quat_normalize(
+                        &(transform.rotation * rotations[*group as usize]),
+                    ));
+                    rotations.len() as u32 - 1
+                });
+            }
+        }
+        (rotations, groups)
+    }
+
+    pub fn undo_label(&self) -> Option<&'static str> {
+        self.undo_stack.last().map(|operation| operation.label)
+    }
+
+    pub fn redo_label(&self) -> Option<&'static str> {
+        self.redo_stack.last().map(|operation| operation.label)
+    }
+
+    pub fn undo(&mut self, gaussians: &mut [GpuGaussian]) -> Option<Range<usize>> {
+        let operation = self.undo_stack.pop()?;
+        let changed = match &operation.change {
+            EditChange::State { before, .. } => {
+                for (index, state) in operation.indices.iter().zip(before) {
+                    self.set_state(*index as usize, *state);
+                }
+                None
+            }
+            EditChange::Transform(transform) => {
+                transform_gaussians(gaussians, &operation.indices, &transform.inverse())
+            }
+        };
+        self.redo_stack.push(operation);
+        changed
+    }
+
+    pub fn redo(&mut self, gaussians: &mut [GpuGaussian]) -> Option<Range<usize>> {
+        let operation = self.redo_stack.pop()?;
+        let changed = match &operation.change {
+            EditChange::State { after, .. } => {
+                for index in &operation.indices {
+                    self.set_state(*index as usize, *after);
+                }
+                None
+            }
+            EditChange::Transform(transform) => {
+                transform_gaussians(gaussians, &operation.indices, transform)
+            }
+        };
+        self.undo_stack.push(operation);
+        changed
+    }
+
+    pub fn take_dirty(&mut self) -> Option<Vec<u32>> {
+        std::mem::take(&mut self.dirty).then(|| {
+            self.inactive
+                .iter()
+                .zip(&self.filtered)
+                .map(|(inactive, filtered)| inactive | filtered)
+                .collect()
+        })
+    }
+
+    fn set_state(&mut self, index: usize, state: GaussianState) {
+        let previous = std::mem::replace(&mut self.states[index], state);
+        let count = |state: GaussianState| match state {
+            GaussianState::Visible => (0, 0),
+            GaussianState::Hidden => (1, 0),
+            GaussianState::Deleted => (0, 1),
+        };
+        let (hidden_before, deleted_before) = count(previous);
+        let (hidden_after, deleted_after) = count(state);
+        self.hidden_count = self.hidden_count + hidden_after - hidden_before;
+        self.deleted_count = self.deleted_count + deleted_after - deleted_before;
+
+        let bit = 1 << (index % 32);
+        if state == GaussianState::Visible {
+            self.inactive[index / 32] &= !bit;
+        } else {
+            self.inactive[index / 32] |= bit;
+        }
+        self.dirty = true;
+    }
+}
+
+fn transform_gaussians(
+    gaussians: &mut [GpuGaussian],
+    indices: &[u32],
+    transform: &SplatTransform,
+) -> Option<Range<usize>> {
+    for index in indices {
+        transform.apply(&mut gaussians[*index as usize]);
+    }
+    let first = *indices.iter().min()? as usize;
+    let last = *indices.iter().max()? as usize;
+    Some(first..last + 1)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn edited_path(ply_path: &std::path::Path) -> std::path::PathBuf {
+    ply_path.with_extension("edited.ply")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gaussian(position: [f32; 3]) -> GpuGaussian {
+        GpuGaussian {
+            position,
+            opacity_logit: 0.0,
+            sh_dc: [0.0; 3],
+            object: 0,
+            scale_log: [-2.0, -3.0, -4.0],
+            filter_variance: 0.0,
+            rotation: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    fn rotation(gaussian: &GpuGaussian) -> Quat {
+        let [w, x, y, z] = gaussian.rotation;
+        Quat::new(w, x, y, z)
+    }
+
+    fn same_rotation(a: &Quat, b: &Quat) -> bool {
+        a.coords.dot(&b.coords).abs() > 1.0 - 1e-5
+    }
+
+    fn assert_close(a: &[GpuGaussian], b: &[GpuGaussian]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((Vec3::from(a.position) - Vec3::from(b.position)).norm() < 1e-5);
+            assert!((Vec3::from(a.scale_log) - Vec3::from(b.scale_log)).norm() < 1e-5);
+            assert!(same_rotation(&rotation(a), &rotation(b)));
+        }
+    }
+
+    #[test]
+    fn state_edits_undo_and_redo() {
+        let mut gaussians = vec![gaussian([0.0; 3]); 40];
+        let mut edits = Edits::new(gaussians.len());
+        assert!(edits.apply("Hide", [1, 2, 35].into_iter(), GaussianState::Hidden));
+        assert!(edits.apply("Delete", [2, 3].into_iter(), GaussianState::Deleted));
+        assert!(!edits.apply("Delete", [2, 3].into_iter(), GaussianState::Deleted));
+        assert_eq!((edits.hidden_count(), edits.deleted_count()), (2, 2));
+        assert_eq!(edits.take_dirty(), Some(vec![0b1110, 1 << 3]));
+        assert_eq!(edits.undo_label(), Some("Delete"));
+
+        assert_eq!(edits.undo(&mut gaussians), None);
+        assert!(edits.state(2) == GaussianState::Hidden);
+        assert!(edits.state(3) == GaussianState::Visible);
+        assert_eq!((edits.hidden_count(), edits.deleted_count()), (3, 0));
+        assert_eq!(edits.redo_label(), Some("Delete"));
+
+        edits.undo(&mut gaussians);
+        assert!((0..40).all(|index| edits.is_active(index)));
+        assert_eq!(edits.take_dirty(), Some(vec![0, 0]));
+        assert_eq!(edits.undo_label(), None);
+
+        edits.redo(&mut gaussians);
+        edits.redo(&mut gaussians);
+        assert!(edits.state(1) == GaussianState::Hidden);
+        assert!(edits.state(2) == GaussianState::Deleted);
+        assert_eq!((edits.hidden_count(), edits.deleted_count()), (2, 2));
+        assert_eq!(edits.redo_label(), None);
+
+        edits.undo(&mut gaussians);
+        assert!(edits.apply("Unhide all", [1].into_iter(), GaussianState::Visible));
+        assert_eq!(edits.redo_label(), None);
+        assert_eq!(edits.redo(&mut gaussians), None);
+    }
+
+    #[test]
+    fn transform_undo_inverts_and_redo_reapplies() {
+        let originals: Vec<GpuGaussian> = (0..6)
+            .map(|index| gaussian([index as f32, 1.0, -2.0]))
+            .collect();
+        let mut gaussians = originals.clone();
+        let mut edits = Edits::new(gaussians.len());
+        let transform = SplatTransform::about_pivot(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.5, -1.0, 2.0),
+            nalgebra_glm::quat_angle_axis(0.9, &Vec3::new(1.0, 2.0, 3.0).normalize()),
+            2.5,
+        );
+        transform_gaussians(&mut gaussians, &[1, 3, 4], &transform);
+        edits.push_transform("Rotate", vec![1, 3, 4], transform);
+        let transformed = gaussians.clone();
+        assert!(Vec3::from(transformed[1].position) != Vec3::from(originals[1].position));
+
+        assert_eq!(edits.undo(&mut gaussians), Some(1..5));
+        assert_close(&gaussians, &originals);
+        assert_eq!(edits.redo(&mut gaussians), Some(1..5));
+        assert_close(&gaussians, &transformed);
+        assert_eq!(gaussians[0].position, originals[0].position);
+        assert_eq!(gaussians[2].position, originals[2].position);
+
+        edits.push_transform("Move", Vec::new(), transform);
+        assert_eq!(edits.undo_label(), Some("Rotate"));
+    }
+
+    #[test]
+    fn rotation_groups_compose_each_gaussians_rotations() {
+        let mut gaussians: Vec<GpuGaussian> = (0..4)
+            .map(|index| gaussian([index as f32, 0.0, 0.0]))
+            .collect();
+        let originals = gaussians.clone();
+        let mut edits = Edits::new(gaussians.len());
+        let operations = [
+            (
+                vec![0, 1, 2],
+                nalgebra_glm::quat_angle_axis(0.6, &Vec3::y()),
+            ),
+            (vec![1, 2], Quat::identity()),
+            (vec![2], nalgebra_glm::quat_angle_axis(-1.1, &Vec3::x())),
+        ];
+        for (indices, rotation) in operations {
+            let transform = SplatTransform::about_pivot(Vec3::zeros(), Vec3::x(), rotation, 1.5);
+            transform_gaussians(&mut gaussians, &indices, &transform);
+            edits.push_transform("Transform", indices, transform);
+        }
+
+        let (rotations, groups) = edits.rotation_groups();
+        assert_eq!(rotations.len(), 3);
+        assert_eq!(groups, [1, 1, 2, 0]);
+        for (index, group) in groups.iter().enumerate() {
+            let delta = rotation(&gaussians[index])
+                * nalgebra_glm::quat_conjugate(&rotation(&originals[index]));
+            assert!(same_rotation(&rotations[*group as usize], &delta));
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use crate::bounds::SceneBounds;
+use crate::gaussian::GpuGaussian;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gaussian::RawGaussian;
+use nightshade::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+pub const DEFAULT_OUTLIER_NEIGHBORS: usize = 8;
+pub const MAX_OUTLIER_NEIGHBORS: usize = 32;
+
+const POINTS_PER_CELL_PER_NEIGHBOR: f32 = 2.0;
+const MAX_SEARCH_RINGS: i32 = 8;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct FilterSettings {
+    pub outlier_neighbors: usize,
+    pub outlier_std_ratio: Option<f32>,
+    pub min_opacity: Option<f32>,
+    pub max_scale: Option<f32>,
+    pub max_aspect_ratio: Option<f32>,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            outlier_neighbors: DEFAULT_OUTLIER_NEIGHBORS,
+            outlier_std_ratio: None,
+            min_opacity: None,
+            max_scale: None,
+            max_aspect_ratio: None,
+        }
+    }
+}
+
+pub struct NeighborDistances {
+    pub neighbors: usize,
+    distances: Vec<f32>,
+    mean: f32,
+    std_dev: f32,
+}
+
+impl NeighborDistances {
+    pub fn compute(gaussians: &[GpuGaussian], neighbors: usize) -> Self {
+        let neighbors = neighbors.clamp(1, MAX_OUTLIER_NEIGHBORS);
+        let positions: Vec<Vec3> = gaussians
+            .iter()
+            .map(|gaussian| Vec3::from(gaussian.position))
+            .collect();
+        let cell_size = SceneBounds::from_gaussians(gaussians).map_or(1.0, |bounds| {
+            let extent = (bounds.max - bounds.min).sup(&Vec3::repeat(1e-6));
+            (extent.x * extent.y * extent.z * POINTS_PER_CELL_PER_NEIGHBOR * neighbors as f32
+                / positions.len() as f32)
+                .cbrt()
+                .max(1e-6)
+        });
+        let cell = |position: &Vec3| {
+            let cell = position.map(|value| (value / cell_size).floor() as i32);
+            [cell.x, cell.y, cell.z]
+        };
+        let mut sorted: Vec<([i32; 3], Vec3)> = positions
+            .iter()
+            .map(|position| (cell(position), *position))
+            .collect();
+        sorted.sort_unstable_by_key(|(cell, _)| *cell);
+        let mut grid: HashMap<[i32; 3], Range<usize>> = HashMap::new();
+        for (index, (cell, _)) in sorted.iter().enumerate() {
+            grid.entry(*cell)
+                .and_modify(|range| range.end = index + 1)
+                .or_insert(index..index + 1);
+        }
+        let sorted: Vec<Vec3> = sorted.into_iter().map(|(_, position)| position).collect();
+
+        let distances: Vec<f32> = positions
+            .iter()
+            .map(|position| {
+                mean_neighbor_distance(
+                    &grid,
+                    &sorted,
+                    cell_size,
+                    cell(position),
+                    position,
+                    neighbors,
+                )
+            })
+            .collect();
+
+        let count = distances.len().max(1) as f32;
+        let mean = distances.iter().sum::<f32>() / count;
+        let variance = distances
+            .iter()
+            .map(|distance| (distance - mean) * (distance - mean))
+            .sum::<f32>()
+            / count;
+        Self {
+            neighbors,
+            distances,
+            mean,
+            std_dev: variance.sqrt(),
+        }
+    }
+
+    pub fn threshold(&self, std_ratio: f32) -> f32 {
+        self.mean + std_ratio * self.std_dev
+    }
+}
+
+fn mean_neighbor_distance(
+    grid: &HashMap<[i32; 3], Range<usize>>,
+    positions: &[Vec3],
+    cell_size: f32,
+    center: [i32; 3],
+    position: &Vec3,
+    neighbors: usize,
+) -> f32 {
+    let cell_min = Vec3::new(center[0] as f32, center[1] as f32, center[2] as f32) * cell_size;
+    let offset = position - cell_min;
+    let margin = offset
+        .inf(&(Vec3::repeat(cell_size) - offset))
+        .min()
+        .max(0.0);
+    let mut nearest: Vec<f32> = Vec::with_capacity(neighbors + 1);
+    let mut skipped_self = false;
+    for ring in 0..=MAX_SEARCH_RINGS {
+        for x in -ring..=ring {
+            for y in -ring..=ring {
+                for z in -ring..=ring {
+                    if x.abs().max(y.abs()).max(z.abs()) != ring {
+                        continue;
+                    }
+                    let step = Vec3::new(x as f32, y as f32, z as f32) * cell_size;
+                    let gap = (cell_min + step - position)
+                        .sup(&(position - cell_min - step - Vec3::repeat(cell_size)))
+                        .sup(&Vec3::zeros());
+                    if nearest.len() == neighbors && gap.norm_squared() >= nearest[neighbors - 1] {
+                        continue;
+                    }
+                    let Some(range) = grid.get(&[center[0] + x, center[1] + y, center[2] + z])
+                    else {
+                        continue;
+                    };
+                    for candidate in &positions[range.clone()] {
+                        let distance = (candidate - position).norm_squared();
+                        if !skipped_self && candidate == position {
+                            skipped_self = true;
+                            continue;
+                        }
+                        if nearest.len() < neighbors || distance < nearest[neighbors - 1] {
+                            let slot = nearest.partition_point(|nearer| *nearer <= distance);
+                            nearest.insert(slot, distance);
+                            nearest.truncate(neighbors);
+                        }
+                    }
+                }
+            }
+        }
+        if nearest.len() == neighbors
+            && nearest[neighbors - 1].sqrt() <= ring as f32 * cell_size + margin
+        {
+            break;
+        }
+    }
+    let unreachable = cell_size * (MAX_SEARCH_RINGS + 1) as f32;
+    nearest.resize(neighbors, unreachable * unreachable);
+    nearest.iter().map(|distance| distance.sqrt()).sum::<f32>() / neighbors as f32
+}
+
+pub fn rejected(
+    gaussians: &[GpuGaussian],
+    settings: &FilterSettings,
+    neighbor_distances: Option<&NeighborDistances>,
+) -> Vec<bool> {
+    let outlier_threshold = settings
+        .outlier_std_ratio
+        .zip(neighbor_distances)
+        .map(|(std_ratio, distances)| (distances, distances.threshold(std_ratio)));
+    gaussians
+        .iter()
+        .enumerate()
+        .map(|(index, gaussian)| {
+            let scale = Vec3::from(gaussian.scale_log);
+            let opacity = 1.0 / (1.0 + (-gaussian.opacity_logit).exp());
+            outlier_threshold
+                .is_some_and(|(distances, threshold)| distances.distances[index] > threshold)
+                || settings.min_opacity.is_some_and(|min| opacity < min)
+                || settings
+                    .max_scale
+                    .is_some_and(|max| scale.max().exp() > max)
+                || settings
+                    .max_aspect_ratio
+                    .is_some_and(|max| (scale.max() - scale.min()).exp() > max)
+        })
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn filter_gaussians(
+    gaussians: Vec<RawGaussian>,
+    settings: &FilterSettings,
+) -> Vec<RawGaussian> {
+    let gpu_gaussians: Vec<GpuGaussian> = gaussians.iter().map(GpuGaussian::from).collect();
+    let neighbor_distances = settings
+        .outlier_std_ratio
+        .map(|_| NeighborDistances::compute(&gpu_gaussians, settings.outlier_neighbors));
+    let rejected = rejected(&gpu_gaussians, settings, neighbor_distances.as_ref());
+    gaussians
+        .into_iter()
+        .zip(rejected)
+        .filter(|(_, rejected)| !rejected)
+        .map(|(gaussian, _)| gaussian)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaussian::RawGaussian;
+
+    fn raw_gaussian(position: [f32; 3], opacity: f32, scale: [f32; 3]) -> RawGaussian {
+        RawGaussian {
+            position,
+            opacity,
+            scale,
+            rotation: [1.0, 0.0, 0.0, 0.0],
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    fn gpu_gaussians(raw: &[RawGaussian]) -> Vec<GpuGaussian> {
+        raw.iter().map(GpuGaussian::from).collect()
+    }
+
+    fn scattered_positions(count: usize) -> Vec<[f32; 3]> {
+        let mut seed = 0x9e37_79b9_u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as f32 / u32::MAX as f32
+        };
+        (0..count)
+            .map(|index| {
+                let spread = if index % 10 == 0 { 4.0 } else { 1.0 };
+                [random() * spread, random() * spread, random() * spread]
+            })
+            .collect()
+    }
+
+    fn brute_force_mean_distance(positions: &[[f32; 3]], index: usize, neighbors: usize) -> f32 {
+        let position = Vec3::from(positions[index]);
+        let mut distances: Vec<f32> = positions
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, other)| (Vec3::from(*other) - position).norm())
+            .collect();
+        distances.sort_by(f32::total_cmp);
+        distances[..neighbors].iter().sum::<f32>() / neighbors as f32
+    }
+
+    #[test]
+    fn ring_search_matches_brute_force() {
+        let positions = scattered_positions(600);
+        let raw: Vec<RawGaussian> = positions
+            .iter()
+            .map(|position| raw_gaussian(*position, 0.0, [0.0; 3]))
+            .collect();
+        for neighbors in [1, 4, DEFAULT_OUTLIER_NEIGHBORS] {
+            let distances = NeighborDistances::compute(&gpu_gaussians(&raw), neighbors);
+            for (index, distance) in distances.distances.iter().enumerate() {
+                let expected = brute_force_mean_distance(&positions, index, neighbors);
+                assert!(
+                    (distance - expected).abs() < 1e-4,
+                    "gaussian {index} with k = {neighbors}: {distance} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn opacity_is_compared_after_the_sigmoid() {
+        let raw = [
+            raw_gaussian([0.0; 3], -3.0, [0.0; 3]),
+            raw_gaussian([0.0; 3], -1.0, [0.0; 3]),
+            raw_gaussian([0.0; 3], 2.0, [0.0; 3]),
+        ];
+        let settings = FilterSettings {
+            min_opacity: Some(0.2),
+            ..FilterSettings::default()
+        };
+        assert_eq!(
+            rejected(&gpu_gaussians(&raw), &settings, None),
+            [true, false, false]
+        );
+    }
+
+    #[test]
+    fn scale_is_compared_after_the_exponential() {
+        let raw = [
+            raw_gaussian([0.0; 3], 0.0, [-2.0, -2.0, 0.5]),
+            raw_gaussian([0.0; 3], 0.0, [-2.0, -1.0, -0.5]),
+        ];
+        let settings = FilterSettings {
+            max_scale: Some(1.0),
+            ..FilterSettings::default()
+        };
+        assert_eq!(
+            rejected(&gpu_gaussians(&raw), &settings, None),
+            [true, false]
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_caps_largest_over_smallest_scale() {
+        let raw = [
+            raw_gaussian([0.0; 3], 0.0, [0.0, 10.0_f32.ln(), 3.0]),
+            raw_gaussian([0.0; 3], 0.0, [-1.0, 0.0, 20.0_f32.ln() - 1.0]),
+            raw_gaussian([0.0; 3], 0.0, [-4.0, -4.0, -4.0]),
+        ];
+        let settings = FilterSettings {
+            max_aspect_ratio: Some(15.0),
+            ..FilterSettings::default()
+        };
+        assert_eq!(
+            rejected(&gpu_gaussians(&raw), &settings, None),
+            [true, true, false]
+        );
+    }
+
+    #[test]
+    fn outliers_beyond_the_std_ratio_are_rejected() {
+        let mut raw: Vec<RawGaussian> = (0..64)
+            .map(|index| {
+                let position = [
+                    (index % 4) as f32 * 0.1,
+                    (index / 4 % 4) as f32 * 0.1,
+                    (index / 16) as f32 * 0.1,
+                ];
+                raw_gaussian(position, 0.0, [0.0; 3])
+            })
+            .collect();
+        raw.push(raw_gaussian([1.5, 0.15, 0.15], 0.0, [0.0; 3]));
+        let gaussians = gpu_gaussians(&raw);
+        let settings = FilterSettings {
+            outlier_neighbors: 4,
+            outlier_std_ratio: Some(3.0),
+            ..FilterSettings::default()
+        };
+        let distances = NeighborDistances::compute(&gaussians, settings.outlier_neighbors);
+        let rejected_gaussians = rejected(&gaussians, &settings, Some(&distances));
+        assert!(rejected_gaussians[64]);
+        assert!(rejected_gaussians[..64].iter().all(|rejected| !rejected));
+        assert!(
+            rejected(&gaussians, &settings, None)
+                .iter()
+                .all(|rejected| !rejected)
+        );
+
+        let kept = filter_gaussians(raw, &settings);
+        assert_eq!(kept.len(), 64);
+    }
+}
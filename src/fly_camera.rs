@@ -0,0 +1,97 @@
+use nightshade::ecs::world::resources::MouseState;
+use nightshade::prelude::*;
+
+const LOOK_RADIANS_PER_PIXEL: f32 = 0.003;
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+const FAST_MULTIPLIER: f32 = 4.0;
+const SCROLL_SPEED_STEP: f32 = 1.2;
+
+pub struct FlyCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub speed: f32,
+}
+
+impl FlyCamera {
+    pub fn from_rotation(rotation: &Quat, speed: f32) -> Self {
+        let (yaw, pitch) =
+            yaw_pitch_from_forward(nalgebra_glm::quat_rotate_vec3(rotation, &-Vec3::z()));
+        Self { yaw, pitch, speed }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        nalgebra_glm::quat_angle_axis(self.yaw, &Vec3::y())
+            * nalgebra_glm::quat_angle_axis(-self.pitch, &Vec3::x())
+    }
+
+    pub fn update(&mut self, world: &mut World) {
+        let Some(entity) = world.resources.active_camera else {
+            return;
+        };
+
+        let (pointer_captured, keyboard_captured) = world
+            .resources
+            .user_interface
+            .state
+            .as_ref()
+            .map_or((false, false), |gui_state| {
+                let context = gui_state.egui_ctx();
+                (
+                    context.wants_pointer_input(),
+                    context.wants_keyboard_input(),
+                )
+            });
+
+        let mouse = &world.resources.input.mouse;
+        if !pointer_captured {
+            if mouse.state.contains(MouseState::LEFT_CLICKED) {
+                self.yaw -= mouse.position_delta.x * LOOK_RADIANS_PER_PIXEL;
+                self.pitch = (self.pitch + mouse.position_delta.y * LOOK_RADIANS_PER_PIXEL)
+                    .clamp(-PITCH_LIMIT, PITCH_LIMIT);
+            }
+            if mouse.state.contains(MouseState::SCROLLED) {
+                self.speed *= SCROLL_SPEED_STEP.powf(mouse.wheel_delta.y);
+            }
+        }
+
+        let keyboard = &world.resources.input.keyboard;
+        let pressed = |key_code: KeyCode| !keyboard_captured && keyboard.is_key_pressed(key_code);
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            pressed(positive) as i32 as f32 - pressed(negative) as i32 as f32
+        };
+        let forward_input = axis(KeyCode::KeyW, KeyCode::KeyS);
+        let right_input = axis(KeyCode::KeyD, KeyCode::KeyA);
+        let up_input = axis(KeyCode::Space, KeyCode::ControlLeft);
+        let fast = pressed(KeyCode::ShiftLeft) || pressed(KeyCode::ShiftRight);
+
+        let rotation = self.rotation();
+        let forward = nalgebra_glm::quat_rotate_vec3(&rotation, &-Vec3::z());
+        let right = nalgebra_glm::quat_rotate_vec3(&rotation, &Vec3::x());
+        let mut direction = forward * forward_input + right * right_input + Vec3::y() * up_input;
+        if direction.norm_squared() > 0.0 {
+            direction.normalize_mut();
+        }
+        let speed = if fast {
+            self.speed * FAST_MULTIPLIER
+        } else {
+            self.speed
+        };
+        let delta_time = world.resources.window.timing.delta_time;
+
+        if let Some(local_transform) = world.get_local_transform_mut(entity) {
+            local_transform.translation += direction * speed * delta_time;
+            local_transform.rotation = rotation;
+        }
+        mark_local_transform_dirty(world, entity);
+    }
+}
+
+pub fn yaw_pitch_from_forward(forward: Vec3) -> (f32, f32) {
+    let forward = forward.normalize();
+    let yaw = (-forward.x).atan2(-forward.z);
+    let pitch = (-forward.y)
+        .clamp(-1.0, 1.0)
+        .asin()
+        .clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    (yaw, pitch)
+}
@@ -1,3 +1,12 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sh_rotation::{SH_COEFFICIENTS_PER_CHANNEL, ShRotation};
+use nightshade::prelude::nalgebra_glm::{DMat3, DVec3};
+use nightshade::prelude::*;
+
+const MIN_MOMENT_VARIANCE: f64 = 1e-24;
+const MIN_MOMENT_OPACITY: f64 = 1e-6;
+const MAX_MOMENT_OPACITY: f64 = 0.99;
+
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct RawGaussian {
@@ -16,9 +25,9 @@ pub struct GpuGaussian {
     pub position: [f32; 3],
     pub opacity_logit: f32,
     pub sh_dc: [f32; 3],
-    pub _pad0: f32,
+    pub object: u32,
     pub scale_log: [f32; 3],
-    pub _pad1: f32,
+    pub filter_variance: f32,
     pub rotation: [f32; 4],
 }
 
@@ -30,10 +39,169 @@ impl From<&RawGaussian> for GpuGaussian {
             position: raw.position,
             opacity_logit: raw.opacity,
             sh_dc: raw.sh_dc,
-            _pad0: 0.0,
+            object: 0,
             scale_log: raw.scale,
-            _pad1: 0.0,
+            filter_variance: 0.0,
             rotation: [qw / length, qx / length, qy / length, qz / length],
         }
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RawGaussian {
+    pub fn apply_edits(&mut self, edited: &GpuGaussian, sh_rotation: Option<&ShRotation>) {
+        let original = GpuGaussian::from(&*self);
+        if original.position == edited.position
+            && original.scale_log == edited.scale_log
+            && original.rotation == edited.rotation
+        {
+            return;
+        }
+        if let Some(sh_rotation) = sh_rotation {
+            self.rotate_sh(sh_rotation);
+        }
+        self.position = edited.position;
+        self.scale = edited.scale_log;
+        self.rotation = edited.rotation;
+    }
+
+    pub fn rotate_sh(&mut self, rotation: &ShRotation) {
+        for coefficients in self.sh_rest.chunks_exact_mut(SH_COEFFICIENTS_PER_CHANNEL) {
+            rotation.apply(coefficients);
+        }
+    }
+
+    pub fn truncate_sh(&mut self, sh_degree: usize) {
+        let kept = crate::ply::sh_rest_count(sh_degree);
+        for coefficients in self.sh_rest.chunks_exact_mut(SH_COEFFICIENTS_PER_CHANNEL) {
+            coefficients[kept..].fill(0.0);
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct SplatTransform {
+    pub rotation: Quat,
+    pub scale: f32,
+    pub translation: Vec3,
+}
+
+impl SplatTransform {
+    pub fn about_pivot(pivot: Vec3, translation: Vec3, rotation: Quat, scale: f32) -> Self {
+        Self {
+            rotation,
+            scale,
+            translation: pivot - nalgebra_glm::quat_rotate_vec3(&rotation, &pivot) * scale
+                + translation,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        let rotation = nalgebra_glm::quat_conjugate(&self.rotation);
+        Self {
+            rotation,
+            scale: 1.0 / self.scale,
+            translation: -nalgebra_glm::quat_rotate_vec3(&rotation, &self.translation) / self.scale,
+        }
+    }
+
+    pub fn apply(&self, gaussian: &mut GpuGaussian) {
+        let position = Vec3::from(gaussian.position);
+        gaussian.position = (nalgebra_glm::quat_rotate_vec3(&self.rotation, &position)
+            * self.scale
+            + self.translation)
+            .into();
+        let [w, x, y, z] = gaussian.rotation;
+        let rotation = nalgebra_glm::quat_normalize(&(self.rotation * Quat::new(w, x, y, z)));
+        gaussian.rotation = [rotation.w, rotation.i, rotation.j, rotation.k];
+        gaussian.scale_log = Vec3::from(gaussian.scale_log)
+            .add_scalar(self.scale.ln())
+            .into();
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct GaussianMoments {
+    mass: f64,
+    first: DVec3,
+    second: DMat3,
+    log_transmittance: f64,
+}
+
+impl Default for GaussianMoments {
+    fn default() -> Self {
+        Self {
+            mass: 0.0,
+            first: DVec3::zeros(),
+            second: DMat3::zeros(),
+            log_transmittance: 0.0,
+        }
+    }
+}
+
+impl From<&GpuGaussian> for GaussianMoments {
+    fn from(gaussian: &GpuGaussian) -> Self {
+        let [w, x, y, z] = gaussian.rotation;
+        let rotation =
+            nalgebra_glm::quat_to_mat3(&nalgebra_glm::quat_normalize(&Quat::new(w, x, y, z)))
+                .cast::<f64>();
+        let scale_log = Vec3::from(gaussian.scale_log).cast::<f64>();
+        let variances = scale_log.map(|scale| (2.0 * scale).exp());
+        let covariance = rotation * DMat3::from_diagonal(&variances) * rotation.transpose();
+        let opacity = 1.0 / (1.0 + (-gaussian.opacity_logit as f64).exp());
+        let mass = (opacity * scale_log.sum().exp()).max(f64::MIN_POSITIVE);
+        let position = Vec3::from(gaussian.position).cast::<f64>();
+        Self {
+            mass,
+            first: position * mass,
+            second: (covariance + position * position.transpose()) * mass,
+            log_transmittance: (1.0 - opacity).max(f64::MIN_POSITIVE).ln(),
+        }
+    }
+}
+
+impl GaussianMoments {
+    pub fn mass(&self) -> f32 {
+        self.mass as f32
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.mass += other.mass;
+        self.first += other.first;
+        self.second += other.second;
+        self.log_transmittance += other.log_transmittance;
+    }
+
+    pub fn fit(&self) -> GpuGaussian {
+        let mass = self.mass.max(f64::MIN_POSITIVE);
+        let mean = self.first / mass;
+        let covariance = self.second / mass - mean * mean.transpose();
+        let eigen = covariance.symmetric_eigen();
+        let mut axes = eigen.eigenvectors;
+        if axes.determinant() < 0.0 {
+            axes.set_column(2, &-axes.column(2));
+        }
+        let rotation = nalgebra_glm::quat_normalize(&nalgebra_glm::mat3_to_quat(&axes));
+        let scale_log = eigen
+            .eigenvalues
+            .map(|variance| 0.5 * variance.max(MIN_MOMENT_VARIANCE).ln());
+        let coverage = 1.0 - self.log_transmittance.exp();
+        let opacity = (mass / scale_log.sum().exp())
+            .min(coverage)
+            .clamp(MIN_MOMENT_OPACITY, MAX_MOMENT_OPACITY);
+        GpuGaussian {
+            position: mean.cast::<f32>().into(),
+            opacity_logit: (opacity / (1.0 - opacity)).ln() as f32,
+            sh_dc: [0.0; 3],
+            object: 0,
+            scale_log: scale_log.cast::<f32>().into(),
+            filter_variance: 0.0,
+            rotation: [
+                rotation.w as f32,
+                rotation.i as f32,
+                rotation.j as f32,
+                rotation.k as f32,
+            ],
+        }
+    }
+}
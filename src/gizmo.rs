@@ -0,0 +1,262 @@
+use crate::crop;
+use crate::gaussian::{GpuGaussian, SplatTransform};
+use nightshade::prelude::*;
+
+pub const GIZMO_SIZE_FRACTION: f32 = 0.15;
+
+const PICK_RADIUS_PIXELS: f32 = 10.0;
+const RING_SEGMENTS: usize = 64;
+const TIP_FRACTION: f32 = 0.12;
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 100.0;
+const AXIS_COLORS: [Vec4; 3] = [
+    Vec4::new(0.95, 0.3, 0.25, 1.0),
+    Vec4::new(0.3, 0.9, 0.4, 1.0),
+    Vec4::new(0.3, 0.5, 1.0, 1.0),
+];
+const UNIFORM_COLOR: Vec4 = Vec4::new(0.9, 0.9, 0.9, 1.0);
+const ACTIVE_COLOR: Vec4 = Vec4::new(1.0, 0.85, 0.2, 1.0);
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            GizmoMode::Translate => "Move",
+            GizmoMode::Rotate => "Rotate",
+            GizmoMode::Scale => "Scale",
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub pivot: Vec3,
+    pub size: f32,
+}
+
+#[derive(Copy, Clone)]
+enum DragAnchor {
+    Parameter(f32),
+    Direction(Vec3),
+    Distance { pivot_screen: Vec2, distance: f32 },
+}
+
+pub struct GizmoDrag {
+    pub gizmo: Gizmo,
+    pub axis: usize,
+    anchor: DragAnchor,
+    pub originals: Vec<(u32, GpuGaussian)>,
+    pub transform: Option<SplatTransform>,
+}
+
+impl Gizmo {
+    fn axis(axis: usize) -> Vec3 {
+        let mut unit = Vec3::zeros();
+        unit[axis] = 1.0;
+        unit
+    }
+
+    fn handle_count(&self) -> usize {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Rotate => 3,
+            GizmoMode::Scale => 1,
+        }
+    }
+
+    fn handle_direction(&self, handle: usize) -> Vec3 {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Rotate => Self::axis(handle),
+            GizmoMode::Scale => Vec3::repeat(1.0).normalize(),
+        }
+    }
+
+    fn handle_points(&self, axis: usize) -> Vec<Vec3> {
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                vec![
+                    self.pivot,
+                    self.pivot + self.handle_direction(axis) * self.size,
+                ]
+            }
+            GizmoMode::Rotate => {
+                let first = Self::axis((axis + 1) % 3) * self.size;
+                let second = Self::axis((axis + 2) % 3) * self.size;
+                (0..=RING_SEGMENTS)
+                    .map(|segment| {
+                        let angle = segment as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                        self.pivot + first * angle.cos() + second * angle.sin()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    pub fn lines(&self, active_axis: Option<usize>) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for (axis, axis_color) in AXIS_COLORS
+            .into_iter()
+            .enumerate()
+            .take(self.handle_count())
+        {
+            let color = if active_axis == Some(axis) {
+                ACTIVE_COLOR
+            } else if self.mode == GizmoMode::Scale {
+                UNIFORM_COLOR
+            } else {
+                axis_color
+            };
+            let points = self.handle_points(axis);
+            for pair in points.windows(2) {
+                lines.push(Line {
+                    start: pair[0],
+                    end: pair[1],
+                    color,
+                });
+            }
+
+            let tip = self.pivot + self.handle_direction(axis) * self.size;
+            let tip_size = self.size * TIP_FRACTION;
+            match self.mode {
+                GizmoMode::Translate => {
+                    let base = tip - Self::axis(axis) * tip_size;
+                    for side in [(axis + 1) % 3, (axis + 2) % 3] {
+                        for sign in [1.0, -1.0] {
+                            lines.push(Line {
+                                start: tip,
+                                end: base + Self::axis(side) * sign * tip_size * 0.5,
+                                color,
+                            });
+                        }
+                    }
+                }
+                GizmoMode::Scale => {
+                    for side in 0..3 {
+                        let offset = Self::axis(side) * tip_size * 0.5;
+                        lines.push(Line {
+                            start: tip - offset,
+                            end: tip + offset,
+                            color,
+                        });
+                    }
+                }
+                GizmoMode::Rotate => {}
+            }
+        }
+        lines
+    }
+
+    pub fn pick(&self, project: impl Fn(Vec3) -> Option<Vec2>, cursor: Vec2) -> Option<usize> {
+        (0..self.handle_count())
+            .filter_map(|axis| {
+                let points: Vec<Option<Vec2>> =
+                    self.handle_points(axis).into_iter().map(&project).collect();
+                let distance = points
+                    .windows(2)
+                    .filter_map(|pair| Some(segment_distance(pair[0]?, pair[1]?, cursor)))
+                    .min_by(f32::total_cmp)?;
+                (distance <= PICK_RADIUS_PIXELS).then_some((axis, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(axis, _)| axis)
+    }
+}
+
+impl GizmoDrag {
+    pub fn begin(
+        gizmo: Gizmo,
+        axis: usize,
+        ray_origin: Vec3,
+        ray_direction: Vec3,
+        cursor: Vec2,
+        project: impl Fn(Vec3) -> Option<Vec2>,
+        originals: Vec<(u32, GpuGaussian)>,
+    ) -> Option<Self> {
+        let direction = Gizmo::axis(axis);
+        let anchor = match gizmo.mode {
+            GizmoMode::Translate => DragAnchor::Parameter(crop::closest_line_parameter(
+                gizmo.pivot,
+                direction,
+                ray_origin,
+                ray_direction,
+            )?),
+            GizmoMode::Rotate => DragAnchor::Direction(
+                crop::intersect_plane(ray_origin, ray_direction, gizmo.pivot, direction)?
+                    - gizmo.pivot,
+            ),
+            GizmoMode::Scale => {
+                let pivot_screen = project(gizmo.pivot)?;
+                DragAnchor::Distance {
+                    pivot_screen,
+                    distance: (cursor - pivot_screen).norm().max(PICK_RADIUS_PIXELS),
+                }
+            }
+        };
+        Some(Self {
+            gizmo,
+            axis,
+            anchor,
+            originals,
+            transform: None,
+        })
+    }
+
+    pub fn world_transform(
+        &self,
+        ray_origin: Vec3,
+        ray_direction: Vec3,
+        cursor: Vec2,
+    ) -> Option<SplatTransform> {
+        let pivot = self.gizmo.pivot;
+        let direction = Gizmo::axis(self.axis);
+        let (translation, rotation, scale) = match self.anchor {
+            DragAnchor::Parameter(start) => {
+                let parameter =
+                    crop::closest_line_parameter(pivot, direction, ray_origin, ray_direction)?;
+                (direction * (parameter - start), Quat::identity(), 1.0)
+            }
+            DragAnchor::Direction(start) => {
+                let current =
+                    crop::intersect_plane(ray_origin, ray_direction, pivot, direction)? - pivot;
+                let angle = direction
+                    .dot(&start.cross(&current))
+                    .atan2(start.dot(&current));
+                (
+                    Vec3::zeros(),
+                    nalgebra_glm::quat_angle_axis(angle, &direction),
+                    1.0,
+                )
+            }
+            DragAnchor::Distance {
+                pivot_screen,
+                distance,
+            } => {
+                let scale = ((cursor - pivot_screen).norm() / distance).clamp(MIN_SCALE, MAX_SCALE);
+                (Vec3::zeros(), Quat::identity(), scale)
+            }
+        };
+        Some(SplatTransform::about_pivot(
+            pivot,
+            translation,
+            rotation,
+            scale,
+        ))
+    }
+}
+
+fn segment_distance(start: Vec2, end: Vec2, point: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.norm_squared();
+    let t = if length_squared > 0.0 {
+        ((point - start).dot(&segment) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (start + segment * t - point).norm()
+}
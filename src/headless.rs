@@ -0,0 +1,272 @@
+use crate::camera_path::{CameraPath, CameraPose};
+use crate::gaussian::GpuGaussian;
+use crate::profiling::StageTimings;
+use crate::splat_object::GpuSplatObject;
+use crate::splat_pass::{SplatPass, SplatSettings, SplatTarget};
+use nightshade::prelude::*;
+
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const BYTES_PER_PIXEL: u32 = 8;
+
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    splat_pass: SplatPass,
+    width: u32,
+    height: u32,
+    bytes_per_row: u32,
+    color_texture: wgpu::Texture,
+    color_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    readback_buffer: wgpu::Buffer,
+}
+
+impl HeadlessRenderer {
+    pub fn new(
+        gaussians: &[GpuGaussian],
+        width: u32,
+        height: u32,
+        settings: SplatSettings,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let adapter = pollster::block_on(wgpu::util::initialize_adapter_from_env_or_default(
+            &instance, None,
+        ))?;
+        eprintln!("Rendering headless on {}", adapter.get_info().name);
+
+        let mut required_limits = wgpu::Limits::default().using_resolution(adapter.limits());
+        required_limits.max_bind_groups = 8;
+
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+                label: Some("Headless Device"),
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                required_limits,
+                ..Default::default()
+            }))?;
+
+        let mut splat_pass = SplatPass::new(&device, gaussians, None, COLOR_FORMAT);
+        splat_pass.set_settings(settings);
+
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let color_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Color Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: COLOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let color_view = color_texture.create_view(&Default::default());
+
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Depth Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth_texture.create_view(&Default::default());
+
+        let bytes_per_row =
+            (width * BYTES_PER_PIXEL).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless Readback Buffer"),
+            size: bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            splat_pass,
+            width,
+            height,
+            bytes_per_row,
+            color_texture,
+            color_view,
+            depth_view,
+            readback_buffer,
+        })
+    }
+
+    pub fn set_objects(&mut self, objects: Vec<GpuSplatObject>) {
+        self.splat_pass.set_objects(objects);
+    }
+
+    pub fn stage_timings(&mut self) -> Result<Option<StageTimings>, Box<dyn std::error::Error>> {
+        self.splat_pass.poll_timings(&self.queue);
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        self.splat_pass.poll_timings(&self.queue);
+        Ok(self.splat_pass.stage_timings())
+    }
+
+    pub fn render(
+        &mut self,
+        pose: &CameraPose,
+    ) -> Result<image::Rgba32FImage, Box<dyn std::error::Error>> {
+        let (width, height) = (self.width, self.height);
+        let (view, projection) = pose.matrices(width, height);
+        self.splat_pass
+            .update_camera(&self.queue, &view, &projection, (width, height));
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless Encoder"),
+            });
+
+        self.splat_pass.encode(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            SplatTarget {
+                color_view: &self.color_view,
+                color_ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_view: &self.depth_view,
+                depth_ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.0),
+                    store: wgpu::StoreOp::Discard,
+                },
+                width,
+                height,
+            },
+        );
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.color_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver.recv()??;
+
+        let image = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            image::Rgba32FImage::from_fn(width, height, |x, y| {
+                let offset = (y * self.bytes_per_row + x * BYTES_PER_PIXEL) as usize;
+                let channel = |index: usize| {
+                    let start = offset + index * 2;
+                    half::f16::from_le_bytes([data[start], data[start + 1]]).to_f32()
+                };
+                image::Rgba([channel(0), channel(1), channel(2), channel(3)])
+            })
+        };
+        self.readback_buffer.unmap();
+
+        Ok(image)
+    }
+}
+
+pub fn save_image(
+    image: &image::Rgba32FImage,
+    path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let is_exr = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exr"));
+    if is_exr {
+        image.save(path)?;
+        return Ok(());
+    }
+
+    let converted = image::RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let [red, green, blue, alpha] = image.get_pixel(x, y).0;
+        let unpremultiply = if alpha > 0.0 { 1.0 / alpha } else { 0.0 };
+        let quantize = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        image::Rgba([
+            quantize(red * unpremultiply),
+            quantize(green * unpremultiply),
+            quantize(blue * unpremultiply),
+            quantize(alpha),
+        ])
+    });
+    converted.save(path)?;
+    Ok(())
+}
+
+pub fn render_sequence(
+    renderer: &mut HeadlessRenderer,
+    camera_path: &CameraPath,
+    frames_per_second: f32,
+    directory: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((start, end)) = camera_path.time_range() else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(directory)?;
+
+    let frame_count = (((end - start) * frames_per_second).round() as usize).max(1);
+    for frame in 0..frame_count {
+        let Some(pose) = camera_path.sample(start + frame as f32 / frames_per_second) else {
+            continue;
+        };
+        let image = renderer.render(&pose)?;
+        let path = directory.join(format!("frame_{frame:05}.png"));
+        save_image(&image, &path)?;
+        eprintln!(
+            "Wrote frame {}/{} to {}",
+            frame + 1,
+            frame_count,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+pub fn print_stage_timings(
+    renderer: &mut HeadlessRenderer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match renderer.stage_timings()? {
+        Some(timings) => eprintln!(
+            "GPU timings: clear {:.2} ms, preprocess {:.2} ms, sort {:.2} ms, render {:.2} ms, total {:.2} ms",
+            timings.clear_ms,
+            timings.preprocess_ms,
+            timings.sort_ms,
+            timings.render_ms,
+            timings.total_ms()
+        ),
+        None => eprintln!("GPU timings unavailable (adapter lacks TIMESTAMP_QUERY)"),
+    }
+    Ok(())
+}
@@ -0,0 +1,534 @@
+use crate::gaussian::{GaussianMoments, GpuGaussian};
+use crate::splat_object::GpuSplatObject;
+use bytemuck::Zeroable;
+use nightshade::prelude::*;
+use std::ops::Range;
+
+pub const DEFAULT_LOD_PIXEL_SIZE: f32 = 2.0;
+pub const LOD_REPRESENTATIVE: u32 = 0x8000_0000;
+pub const LOD_STATE_OPEN: u32 = 2;
+
+const LOD_STATE_CUT: u32 = 1;
+const STATES_PER_WORD: usize = 16;
+const LEAF_CAPACITY: usize = 16;
+const MAX_DEPTH: u32 = 21;
+const EXTENT_SIGMAS: f32 = 3.0;
+
+#[derive(Copy, Clone)]
+struct LodNode {
+    center: Vec3,
+    radius: f32,
+    first_child: u32,
+    child_count: u32,
+    first_gaussian: u32,
+    gaussian_count: u32,
+    object: u32,
+}
+
+#[derive(Copy, Clone)]
+struct NodeSummary {
+    count: u32,
+    moments: GaussianMoments,
+    color: Vec3,
+    filter_variance: f32,
+    min: Vec3,
+    max: Vec3,
+}
+
+impl NodeSummary {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            moments: GaussianMoments::default(),
+            color: Vec3::zeros(),
+            filter_variance: 0.0,
+            min: Vec3::repeat(f32::MAX),
+            max: Vec3::repeat(f32::MIN),
+        }
+    }
+
+    fn add(&mut self, gaussian: &GpuGaussian) {
+        let moments = GaussianMoments::from(gaussian);
+        let mass = moments.mass();
+        let position = Vec3::from(gaussian.position);
+        let extent = Vec3::repeat(EXTENT_SIGMAS * Vec3::from(gaussian.scale_log).max().exp());
+        self.count += 1;
+        self.moments.merge(&moments);
+        self.color += Vec3::from(gaussian.sh_dc) * mass;
+        self.filter_variance += gaussian.filter_variance * mass;
+        self.min = self.min.inf(&(position - extent));
+        self.max = self.max.sup(&(position + extent));
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.moments.merge(&other.moments);
+        self.color += other.color;
+        self.filter_variance += other.filter_variance;
+        self.min = self.min.inf(&other.min);
+        self.max = self.max.sup(&other.max);
+    }
+
+    fn representative(&self, object: u32) -> GpuGaussian {
+        let mass = self.moments.mass().max(f32::MIN_POSITIVE);
+        GpuGaussian {
+            sh_dc: (self.color / mass).into(),
+            object,
+            filter_variance: self.filter_variance / mass,
+            ..self.moments.fit()
+        }
+    }
+}
+
+pub struct LodCut {
+    pub states: Vec<u32>,
+    pub splat_count: usize,
+}
+
+pub struct LodTree {
+    nodes: Vec<LodNode>,
+    roots: Vec<u32>,
+    gaussians: Vec<GpuGaussian>,
+    gaussian_nodes: Vec<u32>,
+    leaf_gaussians: Vec<u32>,
+    summaries: Vec<NodeSummary>,
+    representatives: Vec<GpuGaussian>,
+    inactive: Vec<u32>,
+}
+
+impl LodTree {
+    pub fn build(gaussians: &[GpuGaussian]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            gaussians: gaussians.to_vec(),
+            gaussian_nodes: vec![0; gaussians.len()],
+            leaf_gaussians: Vec::with_capacity(gaussians.len()),
+            summaries: Vec::new(),
+            representatives: Vec::new(),
+            inactive: Vec::new(),
+        };
+        let object_count = gaussians
+            .iter()
+            .map(|gaussian| gaussian.object as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut objects: Vec<Vec<u32>> = vec![Vec::new(); object_count];
+        for (index, gaussian) in gaussians.iter().enumerate() {
+            objects[gaussian.object as usize].push(index as u32);
+        }
+        for (object, mut indices) in objects.into_iter().enumerate() {
+            if indices.is_empty() {
+                continue;
+            }
+            let (min, max) = indices.iter().fold(
+                (Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)),
+                |(min, max), index| {
+                    let position = Vec3::from(gaussians[*index as usize].position);
+                    (min.inf(&position), max.sup(&position))
+                },
+            );
+            let root = tree.push_nodes(1, object as u32);
+            tree.roots.push(root);
+            tree.build_node(
+                &mut indices,
+                root,
+                (min + max) * 0.5,
+                (max - min).max() * 0.5,
+                0,
+            );
+        }
+        tree.refit(vec![true; tree.nodes.len()]);
+        tree
+    }
+
+    fn push_nodes(&mut self, count: usize, object: u32) -> u32 {
+        let first = self.nodes.len() as u32;
+        let node = LodNode {
+            center: Vec3::zeros(),
+            radius: 0.0,
+            first_child: 0,
+            child_count: 0,
+            first_gaussian: 0,
+            gaussian_count: 0,
+            object,
+        };
+        self.nodes.extend(std::iter::repeat_n(node, count));
+        self.summaries
+            .extend(std::iter::repeat_n(NodeSummary::new(), count));
+        self.representatives
+            .extend(std::iter::repeat_n(GpuGaussian::zeroed(), count));
+        first
+    }
+
+    fn build_node(
+        &mut self,
+        indices: &mut [u32],
+        node: u32,
+        center: Vec3,
+        half_size: f32,
+        depth: u32,
+    ) {
+        let object = self.nodes[node as usize].object;
+        if indices.len() <= LEAF_CAPACITY || depth == MAX_DEPTH || half_size <= 0.0 {
+            let lod_node = &mut self.nodes[node as usize];
+            lod_node.first_gaussian = self.leaf_gaussians.len() as u32;
+            lod_node.gaussian_count = indices.len() as u32;
+            for &index in indices.iter() {
+                self.gaussian_nodes[index as usize] = node;
+            }
+            self.leaf_gaussians.extend_from_slice(indices);
+            return;
+        }
+        let gaussians = &self.gaussians;
+        let octant = |index: &u32| {
+            let position = Vec3::from(gaussians[*index as usize].position);
+            (position.x >= center.x) as usize
+                | ((position.y >= center.y) as usize) << 1
+                | ((position.z >= center.z) as usize) << 2
+        };
+        indices.sort_unstable_by_key(octant);
+        let mut octants = Vec::with_capacity(8);
+        let mut start = 0;
+        while start < indices.len() {
+            let current = octant(&indices[start]);
+            let end = start + indices[start..].partition_point(|index| octant(index) == current);
+            octants.push((current, start..end));
+            start = end;
+        }
+        let first_child = self.push_nodes(octants.len(), object);
+        self.nodes[node as usize].first_child = first_child;
+        self.nodes[node as usize].child_count = octants.len() as u32;
+        for (child, (current, range)) in octants.into_iter().enumerate() {
+            let direction = Vec3::new(
+                if current & 1 != 0 { 0.5 } else { -0.5 },
+                if current & 2 != 0 { 0.5 } else { -0.5 },
+                if current & 4 != 0 { 0.5 } else { -0.5 },
+            );
+            self.build_node(
+                &mut indices[range],
+                first_child + child as u32,
+                center + direction * half_size,
+                half_size * 0.5,
+                depth + 1,
+            );
+        }
+    }
+
+    fn is_inactive(&self, index: u32) -> bool {
+        self.inactive
+            .get(index as usize / 32)
+            .is_some_and(|word| word & (1 << (index % 32)) != 0)
+    }
+
+    fn refit(&mut self, mut dirty: Vec<bool>) -> Option<Range<usize>> {
+        let mut changed: Option<Range<usize>> = None;
+        for node in (0..self.nodes.len()).rev() {
+            let lod_node = self.nodes[node];
+            let children = lod_node.first_child as usize
+                ..(lod_node.first_child + lod_node.child_count) as usize;
+            let mut summary = NodeSummary::new();
+            if lod_node.child_count == 0 {
+                if !dirty[node] {
+                    continue;
+                }
+                let leaf = lod_node.first_gaussian as usize
+                    ..(lod_node.first_gaussian + lod_node.gaussian_count) as usize;
+                for &index in &self.leaf_gaussians[leaf] {
+                    if !self.is_inactive(index) {
+                        summary.add(&self.gaussians[index as usize]);
+                    }
+                }
+            } else {
+                if !children.clone().any(|child| dirty[child]) {
+                    continue;
+                }
+                dirty[node] = true;
+                for child in children {
+                    summary.merge(&self.summaries[child]);
+                }
+            }
+            let lod_node = &mut self.nodes[node];
+            lod_node.center = (summary.min + summary.max) * 0.5;
+            lod_node.radius = (summary.max - summary.min).norm() * 0.5;
+            self.representatives[node] = summary.representative(lod_node.object);
+            self.summaries[node] = summary;
+            changed = Some(match changed {
+                Some(range) => node..range.end,
+                None => node..node + 1,
+            });
+        }
+        changed
+    }
+
+    pub fn state_words(&self) -> usize {
+        self.nodes.len().div_ceil(STATES_PER_WORD).max(1)
+    }
+
+    pub fn representatives(&self) -> &[GpuGaussian] {
+        &self.representatives
+    }
+
+    pub fn gaussian_nodes(&self) -> Vec<u32> {
+        self.gaussian_nodes
+            .iter()
+            .copied()
+            .chain((0..self.nodes.len() as u32).map(|node| node | LOD_REPRESENTATIVE))
+            .collect()
+    }
+
+    pub fn set_inactive(&mut self, words: &[u32]) -> Option<Range<usize>> {
+        let mut dirty = vec![false; self.nodes.len()];
+        for word in 0..words.len().max(self.inactive.len()) {
+            let mut changed = words.get(word).copied().unwrap_or(0)
+                ^ self.inactive.get(word).copied().unwrap_or(0);
+            while changed != 0 {
+                let index = word * 32 + changed.trailing_zeros() as usize;
+                if let Some(node) = self.gaussian_nodes.get(index) {
+                    dirty[*node as usize] = true;
+                }
+                changed &= changed - 1;
+            }
+        }
+        self.inactive = words.to_vec();
+        self.refit(dirty)
+    }
+
+    pub fn update_gaussians(
+        &mut self,
+        first: usize,
+        gaussians: &[GpuGaussian],
+    ) -> Option<Range<usize>> {
+        let end = (first + gaussians.len()).min(self.gaussians.len());
+        if first >= end {
+            return None;
+        }
+        self.gaussians[first..end].copy_from_slice(&gaussians[..end - first]);
+        let mut dirty = vec![false; self.nodes.len()];
+        for node in &self.gaussian_nodes[first..end] {
+            dirty[*node as usize] = true;
+        }
+        self.refit(dirty)
+    }
+
+    pub fn gaussian_count(&self) -> usize {
+        self.gaussians.len()
+    }
+
+    pub fn select(
+        &self,
+        view: &Mat4,
+        projection: &Mat4,
+        viewport_height: u32,
+        objects: &[GpuSplatObject],
+        pixel_size: Option<f32>,
+    ) -> LodCut {
+        let mut cut = LodCut {
+            states: vec![0; self.state_words()],
+            splat_count: 0,
+        };
+        let orthographic = projection[(3, 3)] == 1.0;
+        let focal_y = projection[(1, 1)] * viewport_height as f32 * 0.5;
+        let mut stack = Vec::new();
+        for &root in &self.roots {
+            let object = self.nodes[root as usize].object as usize;
+            let Some(object) = objects.get(object).filter(|object| object.visible != 0) else {
+                continue;
+            };
+            let model = Mat4::from(object.model);
+            let model_view = view * model;
+            let model_scale = (0..3)
+                .map(|column| model.fixed_view::<3, 1>(0, column).norm())
+                .fold(0.0, f32::max);
+            stack.push(root);
+            while let Some(node) = stack.pop() {
+                let active = self.summaries[node as usize].count;
+                if active == 0 {
+                    continue;
+                }
+                let lod_node = &self.nodes[node as usize];
+                let radius = lod_node.radius * model_scale;
+                let depth = -(model_view * lod_node.center.push(1.0)).z;
+                if depth < -radius {
+                    continue;
+                }
+                let projected_size = if orthographic {
+                    2.0 * radius * focal_y
+                } else if depth > radius {
+                    2.0 * radius * focal_y / depth
+                } else {
+                    f32::INFINITY
+                };
+                if pixel_size.is_some_and(|pixel_size| projected_size < pixel_size) {
+                    set_state(&mut cut.states, node, LOD_STATE_CUT);
+                    cut.splat_count += 1;
+                    continue;
+                }
+                set_state(&mut cut.states, node, LOD_STATE_OPEN);
+                if lod_node.child_count == 0 {
+                    cut.splat_count += active as usize;
+                }
+                stack.extend(lod_node.first_child..lod_node.first_child + lod_node.child_count);
+            }
+        }
+        cut
+    }
+}
+
+fn set_state(states: &mut [u32], node: u32, state: u32) {
+    let node = node as usize;
+    states[node / STATES_PER_WORD] |= state << ((node % STATES_PER_WORD) * 2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scattered_gaussians(count: usize) -> Vec<GpuGaussian> {
+        let mut seed = 0x2545_f491_u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as f32 / u32::MAX as f32
+        };
+        (0..count)
+            .map(|_| GpuGaussian {
+                position: [random() * 10.0, random() * 10.0, random() * 10.0],
+                opacity_logit: random() * 4.0 - 2.0,
+                sh_dc: [random(), random(), random()],
+                object: 0,
+                scale_log: [random() - 3.0, random() - 3.0, random() - 3.0],
+                filter_variance: 0.0,
+                rotation: [1.0, 0.0, 0.0, 0.0],
+            })
+            .collect()
+    }
+
+    fn state(states: &[u32], node: usize) -> u32 {
+        (states[node / STATES_PER_WORD] >> ((node % STATES_PER_WORD) * 2)) & 3
+    }
+
+    fn assert_fits(representative: &GpuGaussian, expected: &GpuGaussian) {
+        for axis in 0..3 {
+            assert!((representative.position[axis] - expected.position[axis]).abs() < 1e-3);
+            assert!((representative.scale_log[axis] - expected.scale_log[axis]).abs() < 1e-3);
+        }
+        assert!((representative.opacity_logit - expected.opacity_logit).abs() < 1e-3);
+    }
+
+    fn fit(gaussians: &[GpuGaussian], keep: impl Fn(usize) -> bool) -> GpuGaussian {
+        let mut moments = GaussianMoments::default();
+        for (index, gaussian) in gaussians.iter().enumerate() {
+            if keep(index) {
+                moments.merge(&GaussianMoments::from(gaussian));
+            }
+        }
+        moments.fit()
+    }
+
+    fn select(tree: &LodTree, distance: f32) -> LodCut {
+        let view = nalgebra_glm::look_at(
+            &Vec3::new(5.0, 5.0, 5.0 + distance),
+            &Vec3::repeat(5.0),
+            &Vec3::y(),
+        );
+        let projection = PerspectiveCamera::default().matrix_with_aspect(1.0);
+        let objects = [GpuSplatObject::new(Mat4::identity(), true)];
+        tree.select(
+            &view,
+            &projection,
+            720,
+            &objects,
+            Some(DEFAULT_LOD_PIXEL_SIZE),
+        )
+    }
+
+    #[test]
+    fn build_respects_leaf_capacity() {
+        let gaussians = scattered_gaussians(5000);
+        let tree = LodTree::build(&gaussians);
+        let leaves: Vec<&LodNode> = tree
+            .nodes
+            .iter()
+            .filter(|node| node.child_count == 0)
+            .collect();
+        assert!(
+            leaves
+                .iter()
+                .all(|leaf| leaf.gaussian_count as usize <= LEAF_CAPACITY)
+        );
+        assert_eq!(
+            leaves
+                .iter()
+                .map(|leaf| leaf.gaussian_count as usize)
+                .sum::<usize>(),
+            gaussians.len()
+        );
+        assert!(leaves.len() >= gaussians.len().div_ceil(LEAF_CAPACITY));
+        assert!(tree.nodes.len() < 2 * gaussians.len());
+        assert_eq!(tree.representatives().len(), tree.nodes.len());
+        for (index, node) in tree.gaussian_nodes.iter().enumerate() {
+            let leaf = tree.nodes[*node as usize];
+            let range =
+                leaf.first_gaussian as usize..(leaf.first_gaussian + leaf.gaussian_count) as usize;
+            assert!(tree.leaf_gaussians[range].contains(&(index as u32)));
+        }
+    }
+
+    #[test]
+    fn root_representative_matches_all_moments() {
+        let gaussians = scattered_gaussians(2000);
+        let tree = LodTree::build(&gaussians);
+        assert_fits(&tree.representatives()[0], &fit(&gaussians, |_| true));
+    }
+
+    #[test]
+    fn refit_follows_edits_and_moves() {
+        let mut gaussians = scattered_gaussians(2000);
+        let mut tree = LodTree::build(&gaussians);
+
+        let mut words = vec![0u32; gaussians.len().div_ceil(32)];
+        for index in (0..gaussians.len()).step_by(3) {
+            words[index / 32] |= 1 << (index % 32);
+        }
+        assert!(tree.set_inactive(&words).is_some());
+        assert_eq!(
+            tree.summaries[0].count as usize,
+            gaussians.len() - gaussians.len().div_ceil(3)
+        );
+        assert_fits(
+            &tree.representatives()[0],
+            &fit(&gaussians, |index| index % 3 != 0),
+        );
+
+        for gaussian in &mut gaussians[100..400] {
+            gaussian.position[0] += 4.0;
+        }
+        assert!(tree.update_gaussians(100, &gaussians[100..400]).is_some());
+        assert_fits(
+            &tree.representatives()[0],
+            &fit(&gaussians, |index| index % 3 != 0),
+        );
+
+        assert!(tree.set_inactive(&words).is_none());
+    }
+
+    #[test]
+    fn distant_camera_draws_the_root_representative() {
+        let tree = LodTree::build(&scattered_gaussians(2000));
+        let cut = select(&tree, 100_000.0);
+        assert_eq!(state(&cut.states, 0), LOD_STATE_CUT);
+        assert_eq!(cut.splat_count, 1);
+    }
+
+    #[test]
+    fn near_camera_opens_the_root() {
+        let gaussians = scattered_gaussians(2000);
+        let tree = LodTree::build(&gaussians);
+        let cut = select(&tree, 10.0);
+        assert_eq!(state(&cut.states, 0), LOD_STATE_OPEN);
+        assert!(cut.splat_count > 1);
+        assert!(cut.splat_count <= gaussians.len());
+    }
+}
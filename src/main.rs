@@ -1,30 +1,409 @@
+mod bookmarks;
+mod bounds;
+mod camera_path;
+mod cameras;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod crop;
+#[cfg(not(target_arch = "wasm32"))]
+mod decimate;
+mod edits;
+mod filter;
+mod fly_camera;
 mod gaussian;
+mod gizmo;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod lod;
+#[cfg(not(target_arch = "wasm32"))]
+mod merge;
+mod mip;
+mod overdraw;
 mod ply;
+mod profiling;
+mod readback;
+#[cfg(not(target_arch = "wasm32"))]
+mod reference;
+mod scene_transform;
+mod selection;
+#[cfg(not(target_arch = "wasm32"))]
+mod sh_rotation;
+mod splat_object;
 mod splat_pass;
+#[cfg(not(target_arch = "wasm32"))]
+mod streaming;
+mod ui;
 
+use bookmarks::{Bookmark, BookmarkTransition, Bookmarks};
+use bounds::SceneBounds;
+use camera_path::{CameraPath, CameraPose};
+use crop::{CropDrag, CropVolume};
+use edits::{Edits, GaussianState};
+use filter::{FilterSettings, NeighborDistances};
+use fly_camera::FlyCamera;
 use gaussian::GpuGaussian;
+use gizmo::{Gizmo, GizmoDrag, GizmoMode};
+use lod::LodTree;
+use nightshade::ecs::camera::queries::query_active_camera_matrices;
+use nightshade::ecs::world::resources::MouseState;
 use nightshade::prelude::*;
-use splat_pass::SplatPass;
+use overdraw::OverdrawStats;
+use profiling::StageTimings;
+use scene_transform::SceneTransform;
+use selection::{ScreenProjection, Selection, SelectionDrag, SelectionMode, SelectionOperation};
+#[cfg(not(target_arch = "wasm32"))]
+use sh_rotation::ShRotation;
+use splat_object::{GaussianSplat, SCENE_ASSET, SplatAsset};
+use splat_pass::{FilterMode, SplatPass, SplatSettings};
+
+const INITIAL_CAMERA_RADIUS: f32 = 5.0;
+const INITIAL_CAMERA_YAW: f32 = 0.0;
+const INITIAL_CAMERA_PITCH: f32 = std::f32::consts::FRAC_PI_4;
 
-static GAUSSIANS: std::sync::OnceLock<Vec<GpuGaussian>> = std::sync::OnceLock::new();
+const FLY_SPEED_RADII_PER_SECOND: f32 = 0.5;
+const ORTHOGRAPHIC_PULLBACK_RADII: f32 = 2.0;
+const TOP_VIEW_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.011;
+
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+const LASSO_POINT_SPACING_PIXELS: f32 = 4.0;
+
+const KEYFRAME_SPACING_SECONDS: f32 = 2.0;
+const TURNTABLE_SECONDS: f32 = 12.0;
+
+const DEFAULT_OUTLIER_STD_RATIO: f32 = 2.0;
+const DEFAULT_MIN_OPACITY: f32 = 0.05;
+const DEFAULT_MAX_SCALE_RADII: f32 = 0.1;
+const DEFAULT_MAX_ASPECT_RATIO: f32 = 20.0;
+
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_MERGE_DEDUPE_DISTANCE: f32 = 0.005;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let ply_path = std::env::args()
-        .nth(1)
-        .expect("Usage: gaussian_splats <path_to.ply>");
+    let cli::CliArgs {
+        ply_path,
+        camera_position,
+        camera_target,
+        y_fov_rad,
+        width,
+        height,
+        headless_path,
+        cpu_reference_path,
+        camera_path_file,
+        frames_directory,
+        frames_per_second,
+        object_paths,
+        merge_inputs,
+        merge_output,
+        merge_settings,
+        filter_path,
+        filter_settings,
+        decimate_path,
+        decimate_target,
+        chunk_path,
+        chunk_size,
+        gpu_budget_mb,
+    } = cli::CliArgs::parse(std::env::args().skip(1));
+    let ply_path = ply_path.as_path();
+
+    let chunk_file = if streaming::is_chunk_file(ply_path) {
+        let offline = !merge_inputs.is_empty()
+            || filter_path.is_some()
+            || decimate_path.is_some()
+            || chunk_path.is_some()
+            || headless_path.is_some()
+            || cpu_reference_path.is_some()
+            || frames_directory.is_some();
+        if offline {
+            panic!("Chunked scenes can only be viewed; convert and export from the source .ply");
+        }
+        Some(streaming::ChunkFile::open(ply_path)?)
+    } else {
+        None
+    };
+
+    if !merge_inputs.is_empty() {
+        let output = merge_output.unwrap_or_else(|| merge::merged_path(ply_path));
+        let inputs = std::iter::once(ply_path)
+            .chain(merge_inputs.iter().map(|path| path.as_path()))
+            .map(merge::MergeInput::load)
+            .collect();
+        let merged = merge::merge_scenes(inputs, merge_settings);
+        merged.save(&output)?;
+        eprintln!(
+            "Merged {} gaussians (SH degree {}, {} duplicates removed) into {}",
+            merged.gaussians.len(),
+            merged.sh_degree,
+            merged.duplicates_removed,
+            output.display()
+        );
+        return Ok(());
+    }
+
+    let scene = match &chunk_file {
+        Some(chunk_file) => {
+            eprintln!(
+                "Streaming {} gaussians in {} chunks from {}",
+                chunk_file.gaussian_count(),
+                chunk_file.chunk_count(),
+                ply_path.display()
+            );
+            ply::PlyScene {
+                gaussians: Vec::new(),
+                sh_degree: 0,
+            }
+        }
+        None => {
+            let scene = ply::load_ply_scene(ply_path);
+            eprintln!(
+                "Loaded {} gaussians from {}",
+                scene.gaussians.len(),
+                ply_path.display()
+            );
+            scene
+        }
+    };
+
+    if let Some(output) = filter_path {
+        let loaded = scene.gaussians.len();
+        let kept = filter::filter_gaussians(scene.gaussians, &filter_settings);
+        ply::save_ply(&output, &kept, scene.sh_degree)?;
+        eprintln!(
+            "Filtered out {} of {} gaussians, wrote {} to {}",
+            loaded - kept.len(),
+            loaded,
+            kept.len(),
+            output.display()
+        );
+        return Ok(());
+    }
+
+    let raw_gaussians = scene.gaussians;
+
+    let training_cameras = cameras::find_cameras_json(ply_path)
+        .and_then(|cameras_path| cameras::load_cameras_json(&cameras_path))
+        .unwrap_or_default();
+
+    if let Some(output) = decimate_path {
+        let loaded = raw_gaussians.len();
+        let decimated = decimate::decimate(raw_gaussians, &training_cameras, decimate_target);
+        ply::save_ply(&output, &decimated, scene.sh_degree)?;
+        eprintln!(
+            "Decimated {} gaussians to {}, wrote {}",
+            loaded,
+            decimated.len(),
+            output.display()
+        );
+        return Ok(());
+    }
 
-    let raw_gaussians = ply::load_ply(std::path::Path::new(&ply_path));
     let gpu_gaussians: Vec<GpuGaussian> = raw_gaussians.iter().map(GpuGaussian::from).collect();
 
+    if let Some(output) = chunk_path {
+        let mut gaussians = gpu_gaussians;
+        mip::apply_filter_3d(&mut gaussians, &training_cameras);
+        let loaded = gaussians.len();
+        let chunks = streaming::write_chunk_file(&output, gaussians, chunk_size)?;
+        eprintln!(
+            "Wrote {} gaussians in {} chunks to {}",
+            loaded,
+            chunks,
+            output.display()
+        );
+        return Ok(());
+    }
+
     let gaussian_count = gpu_gaussians.len();
-    eprintln!("Loaded {} gaussians from {}", gaussian_count, ply_path);
+    let scene_bounds = match &chunk_file {
+        Some(chunk_file) => Some(chunk_file.bounds()),
+        None => SceneBounds::from_gaussians(&gpu_gaussians),
+    };
+
+    let scene_transform_file = scene_transform::transform_path(ply_path);
+    let scene_transform =
+        scene_transform::load_scene_transform(&scene_transform_file).unwrap_or_default();
 
-    if GAUSSIANS.set(gpu_gaussians).is_err() {
-        panic!("Failed to set gaussians");
+    let (focus, radius) = orbit_framing(scene_bounds.as_ref(), &scene_transform);
+    let camera = CameraPose {
+        position: camera_position.unwrap_or_else(|| {
+            nightshade::ecs::camera::compute_pan_orbit_transform(
+                focus,
+                INITIAL_CAMERA_YAW,
+                INITIAL_CAMERA_PITCH,
+                radius,
+            )
+            .0
+        }),
+        target: camera_target.unwrap_or(focus),
+        y_fov_rad,
+    };
+
+    let bookmarks_file = bookmarks::bookmarks_path(ply_path);
+    let bookmarks = bookmarks::load_bookmarks(&bookmarks_file).unwrap_or_default();
+
+    let camera_path = match &camera_path_file {
+        Some(file) if file.is_file() => camera_path::load_camera_path(file)?,
+        _ => CameraPath::default(),
+    };
+
+    let mut splat_assets = vec![SplatAsset {
+        name: asset_name(ply_path),
+        path: ply_path.to_path_buf(),
+        sh_degree: scene.sh_degree,
+        gaussians: gpu_gaussians,
+        training_cameras: training_cameras.clone(),
+    }];
+    for object_path in &object_paths {
+        let object = ply::load_ply_scene(object_path);
+        let raw_gaussians = object.gaussians;
+        eprintln!(
+            "Loaded {} gaussians from {}",
+            raw_gaussians.len(),
+            object_path.display()
+        );
+        let training_cameras = cameras::find_cameras_json(object_path)
+            .and_then(|cameras_path| cameras::load_cameras_json(&cameras_path))
+            .unwrap_or_default();
+        splat_assets.push(SplatAsset {
+            name: asset_name(object_path),
+            path: object_path.clone(),
+            sh_degree: object.sh_degree,
+            gaussians: raw_gaussians.iter().map(GpuGaussian::from).collect(),
+            training_cameras,
+        });
+    }
+
+    let offline_gaussians = || splat_object::combined_gaussians(&splat_assets);
+    let offline_objects = splat_object::initial_objects(&splat_assets, scene_transform.matrix());
+
+    if let Some(directory) = frames_directory {
+        if camera_path.keyframes.is_empty() {
+            panic!("--frames requires a --camera-path with at least one keyframe");
+        }
+        let mut renderer = headless::HeadlessRenderer::new(
+            &offline_gaussians(),
+            width,
+            height,
+            SplatSettings::default(),
+        )?;
+        renderer.set_objects(offline_objects);
+        headless::render_sequence(&mut renderer, &camera_path, frames_per_second, &directory)?;
+        headless::print_stage_timings(&mut renderer)?;
+        return Ok(());
     }
 
-    launch(GaussianSplatViewer { gaussian_count })?;
+    if let Some(output_path) = headless_path {
+        let mut renderer = headless::HeadlessRenderer::new(
+            &offline_gaussians(),
+            width,
+            height,
+            SplatSettings::default(),
+        )?;
+        renderer.set_objects(offline_objects);
+        let image = renderer.render(&camera)?;
+        headless::save_image(&image, std::path::Path::new(&output_path))?;
+        eprintln!("Wrote headless render to {}", output_path);
+        headless::print_stage_timings(&mut renderer)?;
+        return Ok(());
+    }
+
+    if let Some(output_path) = cpu_reference_path {
+        let (view, projection) = camera.matrices(width, height);
+        let image = reference::render_reference(
+            &offline_gaussians(),
+            &offline_objects,
+            &view,
+            &projection,
+            width,
+            height,
+            SplatSettings::default().filter_mode,
+        );
+        headless::save_image(&image, std::path::Path::new(&output_path))?;
+        eprintln!("Wrote CPU reference render to {}", output_path);
+        return Ok(());
+    }
+
+    launch(GaussianSplatViewer {
+        splat_assets,
+        splat_objects: Vec::new(),
+        selected_object: None,
+        gaussian_count,
+        scene_bounds,
+        scene_transform,
+        scene_transform_file: Some(scene_transform_file),
+        scene_transform_status: None,
+        y_fov_rad: PerspectiveCamera::default().y_fov_rad,
+        projection_mode: ProjectionMode::Perspective,
+        orthographic_half_height: INITIAL_CAMERA_RADIUS,
+        bookmarks,
+        bookmarks_file: Some(bookmarks_file),
+        bookmark_name: String::new(),
+        bookmark_transition: None,
+        bookmark_status: None,
+        training_cameras,
+        training_view: None,
+        fly_camera: None,
+        show_training_frusta: true,
+        frusta_entity: None,
+        frusta_state: None,
+        crop_volumes: Vec::new(),
+        selected_crop: None,
+        show_crop_handles: true,
+        crop_drag: None,
+        crop_entity: None,
+        crop_state: None,
+        selection: Selection::new(gaussian_count),
+        selection_mode: None,
+        selection_drag: None,
+        brush_radius: 0.05,
+        edits: Edits::new(gaussian_count),
+        gizmo_mode: None,
+        gizmo_drag: None,
+        gizmo_entity: None,
+        dirty_gaussians: None,
+        filter_3d_applied: false,
+        ply_file: ply_path.to_path_buf(),
+        crop_status: None,
+        edit_status: None,
+        bake_scene_transform: false,
+        merge_sh_degree: None,
+        remove_merge_duplicates: false,
+        merge_dedupe_distance: DEFAULT_MERGE_DEDUPE_DISTANCE,
+        merge_status: None,
+        filter_settings,
+        applied_filter_settings: FilterSettings::default(),
+        neighbor_distances: None,
+        filter_dirty: false,
+        neighbor_job: None,
+        apply_filters_on_save: true,
+        decimate_target: gaussian_count.min(decimate::DEFAULT_TARGET_COUNT),
+        decimate_status: None,
+        splat_settings: SplatSettings::default(),
+        overdraw_stats: None,
+        lod_splat_count: None,
+        chunk_file,
+        gpu_budget_mb,
+        streamer: None,
+        stream_offset: 0,
+        streaming_stats: None,
+        gpu_timings_supported: false,
+        gpu_timings: None,
+        camera_path,
+        camera_path_file,
+        camera_path_preview_time: None,
+        camera_path_status: None,
+    })?;
 
     Ok(())
 }
@@ -34,40 +413,1299 @@ fn main() {
     console_error_panic_hook::set_once();
 
     wasm_bindgen_futures::spawn_local(async {
-        let bytes = fetch_ply_bytes("assets/truck_point_cloud.ply").await;
+        let bytes = fetch_bytes("assets/truck_point_cloud.ply")
+            .await
+            .expect("Failed to fetch PLY file");
 
-        let raw_gaussians = ply::load_ply_from_bytes(&bytes);
-        let gpu_gaussians: Vec<GpuGaussian> = raw_gaussians.iter().map(GpuGaussian::from).collect();
+        let scene = ply::parse_ply(&bytes);
+        let raw_gaussians = scene.gaussians;
 
-        let gaussian_count = gpu_gaussians.len();
+        let training_cameras = fetch_bytes("assets/cameras.json")
+            .await
+            .and_then(|bytes| cameras::load_cameras_json_from_bytes(&bytes).ok())
+            .unwrap_or_default();
 
-        if GAUSSIANS.set(gpu_gaussians).is_err() {
-            panic!("Failed to set gaussians");
-        }
+        let gpu_gaussians: Vec<GpuGaussian> = raw_gaussians.iter().map(GpuGaussian::from).collect();
+        let gaussian_count = gpu_gaussians.len();
+        let scene_bounds = SceneBounds::from_gaussians(&gpu_gaussians);
 
-        launch(GaussianSplatViewer { gaussian_count }).expect("Failed to launch");
+        launch(GaussianSplatViewer {
+            splat_assets: vec![SplatAsset {
+                name: "truck_point_cloud".to_string(),
+                sh_degree: scene.sh_degree,
+                gaussians: gpu_gaussians,
+                training_cameras: training_cameras.clone(),
+            }],
+            splat_objects: Vec::new(),
+            selected_object: None,
+            gaussian_count,
+            scene_bounds,
+            scene_transform: SceneTransform::default(),
+            scene_transform_file: None,
+            scene_transform_status: None,
+            y_fov_rad: PerspectiveCamera::default().y_fov_rad,
+            projection_mode: ProjectionMode::Perspective,
+            orthographic_half_height: INITIAL_CAMERA_RADIUS,
+            bookmarks: Bookmarks::default(),
+            bookmarks_file: None,
+            bookmark_name: String::new(),
+            bookmark_transition: None,
+            bookmark_status: None,
+            training_cameras,
+            training_view: None,
+            fly_camera: None,
+            show_training_frusta: true,
+            frusta_entity: None,
+            frusta_state: None,
+            crop_volumes: Vec::new(),
+            selected_crop: None,
+            show_crop_handles: true,
+            crop_drag: None,
+            crop_entity: None,
+            crop_state: None,
+            selection: Selection::new(gaussian_count),
+            selection_mode: None,
+            selection_drag: None,
+            brush_radius: 0.05,
+            edits: Edits::new(gaussian_count),
+            filter_settings: FilterSettings::default(),
+            applied_filter_settings: FilterSettings::default(),
+            neighbor_distances: None,
+            filter_dirty: false,
+            gizmo_mode: None,
+            gizmo_drag: None,
+            gizmo_entity: None,
+            dirty_gaussians: None,
+            filter_3d_applied: false,
+            splat_settings: SplatSettings::default(),
+            overdraw_stats: None,
+            lod_splat_count: None,
+            gpu_timings_supported: false,
+            gpu_timings: None,
+            camera_path: CameraPath::default(),
+            camera_path_file: None,
+            camera_path_preview_time: None,
+            camera_path_status: None,
+        })
+        .expect("Failed to launch");
     });
 }
 
 #[cfg(target_arch = "wasm32")]
-async fn fetch_ply_bytes(url: &str) -> Vec<u8> {
+async fn fetch_bytes(url: &str) -> Option<Vec<u8>> {
     use wasm_bindgen::JsCast;
     use wasm_bindgen_futures::JsFuture;
 
     let window = web_sys::window().expect("No window");
-    let response = JsFuture::from(window.fetch_with_str(url))
-        .await
-        .expect("Fetch failed");
+    let response = JsFuture::from(window.fetch_with_str(url)).await.ok()?;
     let response: web_sys::Response = response.dyn_into().expect("Response cast failed");
+    if !response.ok() {
+        return None;
+    }
     let array_buffer = JsFuture::from(response.array_buffer().expect("Array buffer failed"))
         .await
         .expect("Array buffer await failed");
     let uint8_array = js_sys::Uint8Array::new(&array_buffer);
-    uint8_array.to_vec()
+    Some(uint8_array.to_vec())
+}
+
+fn orbit_framing(
+    scene_bounds: Option<&SceneBounds>,
+    scene_transform: &SceneTransform,
+) -> (Vec3, f32) {
+    scene_bounds.map_or((Vec3::zeros(), INITIAL_CAMERA_RADIUS), |bounds| {
+        (
+            scene_transform.transform_point(bounds.center()),
+            bounds.framing_distance(PerspectiveCamera::default().y_fov_rad) * scene_transform.scale,
+        )
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn asset_name(path: &std::path::Path) -> String {
+    path.file_stem().map_or_else(
+        || path.display().to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ProjectionMode {
+    Perspective,
+    Orthographic,
 }
 
 struct GaussianSplatViewer {
+    splat_assets: Vec<SplatAsset>,
+    splat_objects: Vec<(Entity, GaussianSplat)>,
+    selected_object: Option<usize>,
     gaussian_count: usize,
+    scene_bounds: Option<SceneBounds>,
+    scene_transform: SceneTransform,
+    scene_transform_file: Option<std::path::PathBuf>,
+    scene_transform_status: Option<String>,
+    y_fov_rad: f32,
+    projection_mode: ProjectionMode,
+    orthographic_half_height: f32,
+    bookmarks: Bookmarks,
+    bookmarks_file: Option<std::path::PathBuf>,
+    bookmark_name: String,
+    bookmark_transition: Option<BookmarkTransition>,
+    bookmark_status: Option<String>,
+    training_cameras: Vec<cameras::TrainingCamera>,
+    training_view: Option<usize>,
+    fly_camera: Option<FlyCamera>,
+    show_training_frusta: bool,
+    frusta_entity: Option<Entity>,
+    frusta_state: Option<(bool, Option<usize>, SceneTransform)>,
+    crop_volumes: Vec<CropVolume>,
+    selected_crop: Option<usize>,
+    show_crop_handles: bool,
+    crop_drag: Option<CropDrag>,
+    crop_entity: Option<Entity>,
+    crop_state: Option<(Vec<CropVolume>, Option<usize>, bool, SceneTransform)>,
+    selection: Selection,
+    selection_mode: Option<SelectionMode>,
+    selection_drag: Option<SelectionDrag>,
+    brush_radius: f32,
+    edits: Edits,
+    filter_settings: FilterSettings,
+    applied_filter_settings: FilterSettings,
+    neighbor_distances: Option<NeighborDistances>,
+    filter_dirty: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    neighbor_job: Option<(usize, std::sync::mpsc::Receiver<NeighborDistances>)>,
+    gizmo_mode: Option<GizmoMode>,
+    gizmo_drag: Option<GizmoDrag>,
+    gizmo_entity: Option<Entity>,
+    dirty_gaussians: Option<std::ops::Range<usize>>,
+    filter_3d_applied: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    ply_file: std::path::PathBuf,
+    #[cfg(not(target_arch = "wasm32"))]
+    crop_status: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    edit_status: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    bake_scene_transform: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    merge_sh_degree: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    remove_merge_duplicates: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    merge_dedupe_distance: f32,
+    #[cfg(not(target_arch = "wasm32"))]
+    merge_status: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    apply_filters_on_save: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    decimate_target: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    decimate_status: Option<String>,
+    splat_settings: SplatSettings,
+    overdraw_stats: Option<OverdrawStats>,
+    lod_splat_count: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    chunk_file: Option<streaming::ChunkFile>,
+    #[cfg(not(target_arch = "wasm32"))]
+    gpu_budget_mb: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    streamer: Option<streaming::ChunkStreamer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    stream_offset: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    streaming_stats: Option<streaming::StreamingStats>,
+    gpu_timings_supported: bool,
+    gpu_timings: Option<StageTimings>,
+    camera_path: CameraPath,
+    camera_path_file: Option<std::path::PathBuf>,
+    camera_path_preview_time: Option<f32>,
+    camera_path_status: Option<String>,
+}
+
+impl GaussianSplatViewer {
+    fn stop_camera_path_preview(&mut self, world: &mut World) {
+        if self.camera_path_preview_time.take().is_some() {
+            self.resume_navigation(world);
+        }
+    }
+
+    fn resume_navigation(&mut self, world: &mut World) {
+        if self.fly_camera.is_some() {
+            self.enter_fly_mode(world);
+        } else {
+            restore_orbit_camera(world, self.y_fov_rad);
+        }
+    }
+
+    fn scene_radius(&self) -> f32 {
+        self.scene_bounds.map_or(INITIAL_CAMERA_RADIUS, |bounds| {
+            bounds.radius() * self.scene_transform.scale
+        })
+    }
+
+    fn fly_speed(&self) -> f32 {
+        self.scene_radius() * FLY_SPEED_RADII_PER_SECOND
+    }
+
+    fn set_projection_mode(&mut self, world: &mut World, projection_mode: ProjectionMode) {
+        if self.projection_mode == projection_mode {
+            return;
+        }
+        self.projection_mode = projection_mode;
+        if self.fly_camera.is_none()
+            && self.training_view.is_none()
+            && self.camera_path_preview_time.is_none()
+            && self.bookmark_transition.is_none()
+        {
+            restore_orbit_camera(world, self.y_fov_rad);
+        }
+    }
+
+    fn set_orbit_angles(&mut self, world: &mut World, yaw: f32, pitch: f32) {
+        self.set_fly_mode(world, false);
+        let Some(pan_orbit) = world
+            .resources
+            .active_camera
+            .and_then(|entity| world.get_pan_orbit_camera_mut(entity))
+        else {
+            return;
+        };
+        pan_orbit.target_yaw = yaw;
+        pan_orbit.target_pitch = pitch;
+    }
+
+    fn apply_navigation_projection(&mut self, world: &mut World) {
+        let Some(entity) = world.resources.active_camera else {
+            return;
+        };
+
+        let projection = match self.projection_mode {
+            ProjectionMode::Perspective => Projection::Perspective(PerspectiveCamera {
+                y_fov_rad: self.y_fov_rad,
+                ..Default::default()
+            }),
+            ProjectionMode::Orthographic => {
+                let pullback = self.scene_radius() * ORTHOGRAPHIC_PULLBACK_RADII;
+                let Some((focus, yaw, pitch, radius)) =
+                    world.get_pan_orbit_camera(entity).map(|pan_orbit| {
+                        (
+                            pan_orbit.focus,
+                            pan_orbit.yaw,
+                            pan_orbit.pitch,
+                            pan_orbit.radius,
+                        )
+                    })
+                else {
+                    return;
+                };
+                if self.fly_camera.is_none() {
+                    self.orthographic_half_height = radius * (self.y_fov_rad * 0.5).tan();
+                    let (position, rotation) = nightshade::ecs::camera::compute_pan_orbit_transform(
+                        focus,
+                        yaw,
+                        pitch,
+                        radius + pullback,
+                    );
+                    if let Some(local_transform) = world.get_local_transform_mut(entity) {
+                        local_transform.translation = position;
+                        local_transform.rotation = rotation;
+                    }
+                    mark_local_transform_dirty(world, entity);
+                }
+                let aspect_ratio =
+                    nightshade::ecs::camera::queries::query_window_aspect_ratio(world)
+                        .unwrap_or(1.0);
+                Projection::Orthographic(OrthographicCamera {
+                    x_mag: self.orthographic_half_height * aspect_ratio,
+                    y_mag: self.orthographic_half_height,
+                    z_near: OrthographicCamera::default().z_near,
+                    z_far: radius + pullback * 2.0,
+                })
+            }
+        };
+        if let Some(camera) = world.get_camera_mut(entity) {
+            camera.projection = projection;
+        }
+    }
+
+    fn set_fly_mode(&mut self, world: &mut World, enabled: bool) {
+        let navigating = self.training_view.is_none()
+            && self.camera_path_preview_time.is_none()
+            && self.bookmark_transition.is_none();
+        if navigating && self.fly_camera.is_some() == enabled {
+            return;
+        }
+        self.training_view = None;
+        self.camera_path_preview_time = None;
+        self.bookmark_transition = None;
+
+        if enabled {
+            self.enter_fly_mode(world);
+        } else {
+            self.enter_orbit_mode(world);
+        }
+    }
+
+    fn enter_fly_mode(&mut self, world: &mut World) {
+        let Some((position, rotation)) = active_camera_transform(world) else {
+            return;
+        };
+        let speed = self
+            .fly_camera
+            .as_ref()
+            .map_or_else(|| self.fly_speed(), |fly_camera| fly_camera.speed);
+        self.fly_camera = Some(FlyCamera::from_rotation(&rotation, speed));
+        set_camera_view(world, position, rotation, self.y_fov_rad);
+    }
+
+    fn enter_orbit_mode(&mut self, world: &mut World) {
+        self.fly_camera = None;
+        let Some((position, rotation)) = active_camera_transform(world) else {
+            return;
+        };
+        let forward = nalgebra_glm::quat_rotate_vec3(&rotation, &-Vec3::z());
+        let (yaw, pitch) = fly_camera::yaw_pitch_from_forward(forward);
+        let (focus, framing_radius) =
+            orbit_framing(self.scene_bounds.as_ref(), &self.scene_transform);
+        let radius = (focus - position).dot(&forward).max(framing_radius * 0.05);
+        if let Some(pan_orbit) = world
+            .resources
+            .active_camera
+            .and_then(|entity| world.get_pan_orbit_camera_mut(entity))
+        {
+            pan_orbit.focus = position + forward * radius;
+            pan_orbit.target_focus = pan_orbit.focus;
+            pan_orbit.radius = radius;
+            pan_orbit.target_radius = radius;
+            pan_orbit.yaw = yaw;
+            pan_orbit.target_yaw = yaw;
+            pan_orbit.pitch = pitch;
+            pan_orbit.target_pitch = pitch;
+        }
+        restore_orbit_camera(world, self.y_fov_rad);
+    }
+
+    fn frame_scene(&mut self, world: &mut World) {
+        self.camera_path_preview_time = None;
+        self.training_view = None;
+        self.bookmark_transition = None;
+        self.fly_camera = None;
+        restore_orbit_camera(world, self.y_fov_rad);
+
+        let (focus, radius) = orbit_framing(self.scene_bounds.as_ref(), &self.scene_transform);
+        let Some(pan_orbit) = world
+            .resources
+            .active_camera
+            .and_then(|entity| world.get_pan_orbit_camera_mut(entity))
+        else {
+            return;
+        };
+        pan_orbit.target_focus = focus;
+        pan_orbit.target_radius = radius;
+        pan_orbit.target_yaw = INITIAL_CAMERA_YAW;
+        pan_orbit.target_pitch = INITIAL_CAMERA_PITCH;
+    }
+
+    fn set_scene_transform(&mut self, world: &mut World, transform: SceneTransform) {
+        self.scene_transform = transform;
+        if let Some(index) = self.training_view {
+            self.select_training_view(world, Some(index));
+        }
+        if let Some(file) = &self.scene_transform_file
+            && let Err(error) = scene_transform::save_scene_transform(file, &transform)
+        {
+            self.scene_transform_status =
+                Some(format!("Failed to save {}: {}", file.display(), error));
+        }
+    }
+
+    fn add_bookmark(&mut self, world: &World) {
+        let Some((position, rotation)) = active_camera_transform(world) else {
+            return;
+        };
+        let name = match self.bookmark_name.trim() {
+            "" => format!("View {}", self.bookmarks.bookmarks.len() + 1),
+            name => name.to_string(),
+        };
+        self.bookmarks.bookmarks.push(Bookmark::new(
+            name,
+            position,
+            rotation,
+            active_camera_y_fov(world),
+        ));
+        self.bookmark_name.clear();
+        self.save_bookmarks();
+    }
+
+    fn recall_bookmark(&mut self, world: &World, index: usize) {
+        let Some(bookmark) = self.bookmarks.bookmarks.get(index) else {
+            return;
+        };
+        let Some((position, rotation)) = active_camera_transform(world) else {
+            return;
+        };
+        self.training_view = None;
+        self.camera_path_preview_time = None;
+        let current = Bookmark::new(
+            String::new(),
+            position,
+            rotation,
+            active_camera_y_fov(world),
+        );
+        self.bookmark_transition = Some(BookmarkTransition::new(current, bookmark.clone()));
+    }
+
+    fn save_bookmarks(&mut self) {
+        let Some(file) = &self.bookmarks_file else {
+            return;
+        };
+        self.bookmark_status = match bookmarks::save_bookmarks(file, &self.bookmarks) {
+            Ok(()) => None,
+            Err(error) => Some(format!("Failed to save {}: {}", file.display(), error)),
+        };
+    }
+
+    fn start_camera_path_preview(&mut self) {
+        self.training_view = None;
+        self.bookmark_transition = None;
+        self.camera_path_preview_time = self.camera_path.time_range().map(|(start, _)| start);
+    }
+
+    fn select_training_view(&mut self, world: &mut World, index: Option<usize>) {
+        self.camera_path_preview_time = None;
+        self.bookmark_transition = None;
+        self.training_view = index;
+
+        let Some(camera) = index.and_then(|index| self.training_cameras.get(index)) else {
+            self.resume_navigation(world);
+            return;
+        };
+
+        let axis = |column: usize, sign: f32| {
+            Vec3::new(
+                camera.rotation[0][column],
+                camera.rotation[1][column],
+                camera.rotation[2][column],
+            ) * sign
+        };
+        let rotation = nalgebra_glm::mat3_to_quat(&nalgebra_glm::Mat3::from_columns(&[
+            axis(0, 1.0),
+            axis(1, -1.0),
+            axis(2, -1.0),
+        ]));
+        set_camera_view(
+            world,
+            self.scene_transform
+                .transform_point(Vec3::from(camera.position)),
+            self.scene_transform.rotation_quat() * rotation,
+            camera.y_fov_rad(),
+        );
+    }
+
+    fn step_training_view(&mut self, world: &mut World, step: isize) {
+        let count = self.training_cameras.len();
+        if count == 0 {
+            return;
+        }
+        let index = match self.training_view {
+            Some(index) => (index as isize + step).rem_euclid(count as isize) as usize,
+            None if step > 0 => 0,
+            None => count - 1,
+        };
+        self.select_training_view(world, Some(index));
+    }
+
+    fn update_frusta(&mut self, world: &mut World) {
+        let state = (
+            self.show_training_frusta,
+            self.training_view,
+            self.scene_transform,
+        );
+        if self.training_cameras.is_empty() || self.frusta_state == Some(state) {
+            return;
+        }
+        self.frusta_state = Some(state);
+
+        let entity = *self.frusta_entity.get_or_insert_with(|| {
+            world.spawn_entities(
+                LINES | VISIBILITY | LOCAL_TRANSFORM | GLOBAL_TRANSFORM | LOCAL_TRANSFORM_DIRTY,
+                1,
+            )[0]
+        });
+        if let Some(visibility) = world.get_visibility_mut(entity) {
+            visibility.visible = self.show_training_frusta;
+        }
+        if let Some(lines) = world.get_lines_mut(entity) {
+            lines.lines = cameras::frustum_lines(&self.training_cameras, self.training_view);
+            lines.mark_dirty();
+        }
+        if let Some(local_transform) = world.get_local_transform_mut(entity) {
+            local_transform.translation = Vec3::from(self.scene_transform.translation);
+            local_transform.rotation = self.scene_transform.rotation_quat();
+            local_transform.scale = Vec3::repeat(self.scene_transform.scale);
+        }
+        mark_local_transform_dirty(world, entity);
+    }
+
+    fn update_scene_object(&mut self, world: &mut World) {
+        let Some((entity, _)) = self.splat_objects.first() else {
+            return;
+        };
+        let entity = *entity;
+        let translation = Vec3::from(self.scene_transform.translation);
+        let rotation = self.scene_transform.rotation_quat();
+        let scale = Vec3::repeat(self.scene_transform.scale);
+        if let Some(local_transform) = world.get_local_transform_mut(entity)
+            && (local_transform.translation != translation
+                || local_transform.rotation != rotation
+                || local_transform.scale != scale)
+        {
+            local_transform.translation = translation;
+            local_transform.rotation = rotation;
+            local_transform.scale = scale;
+            mark_local_transform_dirty(world, entity);
+        }
+    }
+
+    fn update_crop_drag(&mut self, world: &World) -> bool {
+        let mouse = &world.resources.input.mouse;
+        if !mouse.state.contains(MouseState::LEFT_CLICKED) {
+            self.crop_drag = None;
+            return false;
+        }
+        let Some(matrices) = query_active_camera_matrices(world) else {
+            return false;
+        };
+        let Some(ray) = PickingRay::from_screen_position(world, mouse.position) else {
+            return false;
+        };
+        let transform = self.scene_transform;
+        let ray_origin = transform.inverse_transform_point(ray.origin);
+        let ray_direction = transform
+            .inverse_transform_vector(ray.direction)
+            .normalize();
+        let view = matrices.view;
+        let view_direction = transform
+            .inverse_transform_vector(-Vec3::new(view[(2, 0)], view[(2, 1)], view[(2, 2)]))
+            .normalize();
+
+        if mouse.state.contains(MouseState::LEFT_JUST_PRESSED)
+            && self.show_crop_handles
+            && !pointer_captured(world)
+            && let Some((width, height)) = world.resources.window.cached_viewport_size
+        {
+            let view_projection = matrices.projection * view;
+            let project = |point: Vec3| {
+                project_to_screen(
+                    &view_projection,
+                    (width, height),
+                    transform.transform_point(point),
+                )
+            };
+            if let Some((index, handle)) =
+                crop::pick_handle(&self.crop_volumes, project, mouse.position)
+            {
+                self.selected_crop = Some(index);
+                self.crop_drag = Some(crop::begin_drag(
+                    &self.crop_volumes[index],
+                    index,
+                    handle,
+                    ray_origin,
+                    ray_direction,
+                    view_direction,
+                ));
+            }
+        }
+
+        let Some(drag) = self.crop_drag else {
+            return false;
+        };
+        if let Some(volume) = self.crop_volumes.get_mut(drag.volume) {
+            crop::apply_drag(&drag, volume, ray_origin, ray_direction, view_direction);
+        }
+        true
+    }
+
+    fn update_selection(&mut self, world: &World) -> bool {
+        let Some(mode) = self.selection_mode else {
+            return false;
+        };
+        let mouse = &world.resources.input.mouse;
+        if mouse.state.contains(MouseState::LEFT_JUST_PRESSED) && !pointer_captured(world) {
+            let keyboard = &world.resources.input.keyboard;
+            let pressed = |left: KeyCode, right: KeyCode| {
+                keyboard.is_key_pressed(left) || keyboard.is_key_pressed(right)
+            };
+            let operation = if pressed(KeyCode::ShiftLeft, KeyCode::ShiftRight) {
+                SelectionOperation::Add
+            } else if pressed(KeyCode::AltLeft, KeyCode::AltRight) {
+                SelectionOperation::Subtract
+            } else {
+                SelectionOperation::Replace
+            };
+            self.selection_drag = Some(SelectionDrag {
+                operation,
+                points: vec![mouse.position],
+            });
+        }
+
+        let Some(drag) = self.selection_drag.as_mut() else {
+            return false;
+        };
+        let cursor = mouse.position;
+        match mode {
+            SelectionMode::Rectangle => {
+                drag.points.truncate(1);
+                drag.points.push(cursor);
+            }
+            SelectionMode::Lasso => {
+                if drag
+                    .points
+                    .last()
+                    .is_none_or(|last| (last - cursor).norm() >= LASSO_POINT_SPACING_PIXELS)
+                {
+                    drag.points.push(cursor);
+                }
+            }
+            SelectionMode::Click | SelectionMode::Brush => {}
+        }
+
+        let released = !mouse.state.contains(MouseState::LEFT_CLICKED);
+        if mode == SelectionMode::Brush || released {
+            self.apply_selection(world, mode, cursor);
+        }
+        if released {
+            self.selection_drag = None;
+        }
+        true
+    }
+
+    fn apply_selection(&mut self, world: &World, mode: SelectionMode, cursor: Vec2) {
+        let Some(drag) = self.selection_drag.as_mut() else {
+            return;
+        };
+        let Some(matrices) = query_active_camera_matrices(world) else {
+            return;
+        };
+        let Some((width, height)) = world.resources.window.cached_viewport_size else {
+            return;
+        };
+        let gaussians = &self.splat_assets[SCENE_ASSET].gaussians;
+        let projection = ScreenProjection::new(
+            &self.scene_transform.matrix(),
+            &matrices.view,
+            &matrices.projection,
+            Vec2::new(width as f32, height as f32),
+        );
+        let splats =
+            || selection::visible_splats(gaussians, &projection, &self.crop_volumes, &self.edits);
+
+        let operation = drag.operation;
+        match mode {
+            SelectionMode::Click => self
+                .selection
+                .apply(operation, selection::pick(splats(), cursor).into_iter()),
+            SelectionMode::Rectangle => self.selection.apply(
+                operation,
+                selection::in_rectangle(splats(), drag.points[0], cursor),
+            ),
+            SelectionMode::Lasso => self
+                .selection
+                .apply(operation, selection::in_lasso(splats(), &drag.points)),
+            SelectionMode::Brush => {
+                let Some(hit) = selection::pick(splats(), cursor) else {
+                    return;
+                };
+                let radius = self.brush_radius
+                    * self
+                        .scene_bounds
+                        .map_or(INITIAL_CAMERA_RADIUS, |bounds| bounds.radius());
+                self.selection.apply(
+                    operation,
+                    selection::in_sphere(
+                        splats(),
+                        gaussians,
+                        Vec3::from(gaussians[hit].position),
+                        radius,
+                    ),
+                );
+                if operation == SelectionOperation::Replace {
+                    drag.operation = SelectionOperation::Add;
+                }
+            }
+        }
+    }
+
+    fn update_crop_lines(&mut self, world: &mut World) {
+        let state = (
+            self.crop_volumes.clone(),
+            self.selected_crop,
+            self.show_crop_handles,
+            self.scene_transform,
+        );
+        if self.crop_state.as_ref() == Some(&state) {
+            return;
+        }
+        self.crop_state = Some(state);
+
+        let entity = *self.crop_entity.get_or_insert_with(|| {
+            world.spawn_entities(
+                LINES | VISIBILITY | LOCAL_TRANSFORM | GLOBAL_TRANSFORM | LOCAL_TRANSFORM_DIRTY,
+                1,
+            )[0]
+        });
+        if let Some(visibility) = world.get_visibility_mut(entity) {
+            visibility.visible = self.show_crop_handles && !self.crop_volumes.is_empty();
+        }
+        if let Some(lines) = world.get_lines_mut(entity) {
+            lines.lines = crop::crop_lines(&self.crop_volumes, self.selected_crop);
+            lines.mark_dirty();
+        }
+        if let Some(local_transform) = world.get_local_transform_mut(entity) {
+            local_transform.translation = Vec3::from(self.scene_transform.translation);
+            local_transform.rotation = self.scene_transform.rotation_quat();
+            local_transform.scale = Vec3::repeat(self.scene_transform.scale);
+        }
+        mark_local_transform_dirty(world, entity);
+    }
+
+    fn selection_centroid(&self) -> Option<Vec3> {
+        let (sum, count) = self
+            .selection
+            .iter()
+            .fold((Vec3::zeros(), 0), |(sum, count), index| {
+                (
+                    sum + Vec3::from(self.splat_assets[SCENE_ASSET].gaussians[index].position),
+                    count + 1,
+                )
+            });
+        (count > 0).then(|| sum / count as f32)
+    }
+
+    fn gizmo(&self, world: &World) -> Option<Gizmo> {
+        let mode = self.gizmo_mode?;
+        let pivot = self
+            .scene_transform
+            .transform_point(self.selection_centroid()?);
+        let camera_position = query_active_camera_matrices(world)?.camera_position;
+        Some(Gizmo {
+            mode,
+            pivot,
+            size: (camera_position - pivot).norm() * gizmo::GIZMO_SIZE_FRACTION,
+        })
+    }
+
+    fn update_gizmo_drag(&mut self, world: &World) -> bool {
+        let mouse = &world.resources.input.mouse;
+        if !mouse.state.contains(MouseState::LEFT_CLICKED) {
+            if let Some(drag) = self.gizmo_drag.take()
+                && let Some(transform) = drag.transform
+            {
+                let indices = drag.originals.iter().map(|(index, _)| *index).collect();
+                self.edits
+                    .push_transform(drag.gizmo.mode.label(), indices, transform);
+                self.invalidate_filter();
+            }
+            return false;
+        }
+        let Some(matrices) = query_active_camera_matrices(world) else {
+            return false;
+        };
+        let Some(viewport) = world.resources.window.cached_viewport_size else {
+            return false;
+        };
+        let Some(ray) = PickingRay::from_screen_position(world, mouse.position) else {
+            return false;
+        };
+        let view_projection = matrices.projection * matrices.view;
+        let project = |point: Vec3| project_to_screen(&view_projection, viewport, point);
+
+        if mouse.state.contains(MouseState::LEFT_JUST_PRESSED)
+            && !pointer_captured(world)
+            && let Some(gizmo) = self.gizmo(world)
+            && let Some(axis) = gizmo.pick(project, mouse.position)
+        {
+            let originals = self
+                .selection
+                .iter()
+                .map(|index| {
+                    (
+                        index as u32,
+                        self.splat_assets[SCENE_ASSET].gaussians[index],
+                    )
+                })
+                .collect();
+            self.gizmo_drag = GizmoDrag::begin(
+                gizmo,
+                axis,
+                ray.origin,
+                ray.direction,
+                mouse.position,
+                project,
+                originals,
+            );
+        }
+
+        let Some(drag) = self.gizmo_drag.as_mut() else {
+            return false;
+        };
+        let Some(transform) = drag.world_transform(ray.origin, ray.direction, mouse.position)
+        else {
+            return true;
+        };
+        let transform = self.scene_transform.model_transform(&transform);
+        for (index, original) in &drag.originals {
+            let mut gaussian = *original;
+            transform.apply(&mut gaussian);
+            self.splat_assets[SCENE_ASSET].gaussians[*index as usize] = gaussian;
+        }
+        drag.transform = Some(transform);
+        if let (Some((first, _)), Some((last, _))) = (drag.originals.first(), drag.originals.last())
+        {
+            let range = *first as usize..*last as usize + 1;
+            self.mark_gaussians_dirty(range);
+        }
+        true
+    }
+
+    fn update_gizmo_lines(&mut self, world: &mut World) {
+        let gizmo = self.gizmo(world);
+        let Some(entity) = self.gizmo_entity.or_else(|| {
+            gizmo.is_some().then(|| {
+                world.spawn_entities(LINES | VISIBILITY | LOCAL_TRANSFORM | GLOBAL_TRANSFORM, 1)[0]
+            })
+        }) else {
+            return;
+        };
+        self.gizmo_entity = Some(entity);
+        if let Some(visibility) = world.get_visibility_mut(entity) {
+            visibility.visible = gizmo.is_some();
+        }
+        if let Some(gizmo) = gizmo
+            && let Some(lines) = world.get_lines_mut(entity)
+        {
+            let active_axis = self.gizmo_drag.as_ref().map(|drag| drag.axis);
+            lines.lines = gizmo.lines(active_axis);
+            lines.mark_dirty();
+        }
+    }
+
+    fn mark_gaussians_dirty(&mut self, range: std::ops::Range<usize>) {
+        self.dirty_gaussians = Some(match self.dirty_gaussians.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    fn undo(&mut self) {
+        if let Some(range) = self
+            .edits
+            .undo(&mut self.splat_assets[SCENE_ASSET].gaussians)
+        {
+            self.mark_gaussians_dirty(range);
+            self.invalidate_filter();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(range) = self
+            .edits
+            .redo(&mut self.splat_assets[SCENE_ASSET].gaussians)
+        {
+            self.mark_gaussians_dirty(range);
+            self.invalidate_filter();
+        }
+    }
+
+    fn edit_selection(&mut self, label: &'static str, state: GaussianState) {
+        if self.edits.apply(label, self.selection.iter(), state) {
+            self.selection.clear();
+        }
+    }
+
+    fn isolate_selection(&mut self) {
+        if self.selection.count() == 0 {
+            return;
+        }
+        let unselected: Vec<usize> = self
+            .edits
+            .visible()
+            .filter(|index| !self.selection.contains(*index))
+            .collect();
+        self.edits
+            .apply("Isolate", unselected.into_iter(), GaussianState::Hidden);
+    }
+
+    fn unhide_all(&mut self) {
+        let hidden: Vec<usize> = self.edits.hidden().collect();
+        self.edits
+            .apply("Unhide all", hidden.into_iter(), GaussianState::Visible);
+    }
+
+    fn invalidate_filter(&mut self) {
+        self.neighbor_distances = None;
+        self.filter_dirty = true;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.neighbor_job = None;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn request_neighbor_distances(&mut self, neighbors: usize) {
+        if self
+            .neighbor_job
+            .as_ref()
+            .is_some_and(|(pending, _)| *pending == neighbors)
+        {
+            return;
+        }
+        let gaussians = self.splat_assets[SCENE_ASSET].gaussians.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            sender
+                .send(NeighborDistances::compute(&gaussians, neighbors))
+                .ok();
+        });
+        self.neighbor_job = Some((neighbors, receiver));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn request_neighbor_distances(&mut self, neighbors: usize) {
+        self.neighbor_distances = Some(NeighborDistances::compute(
+            &self.splat_assets[SCENE_ASSET].gaussians,
+            neighbors,
+        ));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn neighbor_distances_pending(&self) -> bool {
+        self.neighbor_job.is_some()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn neighbor_distances_pending(&self) -> bool {
+        false
+    }
+
+    fn update_filter(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((_, receiver)) = &self.neighbor_job
+            && let Ok(distances) = receiver.try_recv()
+        {
+            self.neighbor_job = None;
+            self.neighbor_distances = Some(distances);
+            self.filter_dirty = true;
+        }
+        if !self.filter_dirty && self.filter_settings == self.applied_filter_settings {
+            return;
+        }
+        self.filter_dirty = false;
+        self.applied_filter_settings = self.filter_settings;
+        let neighbors = self.filter_settings.outlier_neighbors;
+        if self.filter_settings.outlier_std_ratio.is_some()
+            && self
+                .neighbor_distances
+                .as_ref()
+                .is_none_or(|distances| distances.neighbors != neighbors)
+        {
+            self.neighbor_distances = None;
+            self.request_neighbor_distances(neighbors);
+        }
+        self.edits.set_filtered(&filter::rejected(
+            &self.splat_assets[SCENE_ASSET].gaussians,
+            &self.filter_settings,
+            self.neighbor_distances.as_ref(),
+        ));
+    }
+
+    fn apply_filter_3d(&mut self, splat_pass: &mut SplatPass) {
+        self.filter_3d_applied = true;
+        if self
+            .splat_assets
+            .iter()
+            .all(|asset| asset.training_cameras.is_empty())
+        {
+            return;
+        }
+        for asset in &mut self.splat_assets {
+            mip::apply_filter_3d(&mut asset.gaussians, &asset.training_cameras);
+        }
+        splat_pass.update_gaussians(0, &splat_object::combined_gaussians(&self.splat_assets));
+    }
+
+    fn delete_filtered(&mut self) {
+        let filtered: Vec<usize> = self.edits.filtered().collect();
+        self.edits.apply(
+            "Delete filtered",
+            filtered.into_iter(),
+            GaussianState::Deleted,
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn edited_scene(&self, apply_crop: bool) -> ply::PlyScene {
+        let scene = ply::load_ply_scene(&self.ply_file);
+        let (rotations, groups) = self.edits.rotation_groups();
+        let sh_rotations: Vec<Option<ShRotation>> = rotations
+            .iter()
+            .enumerate()
+            .map(|(group, rotation)| (group > 0).then(|| ShRotation::new(rotation)))
+            .collect();
+        let gaussians = scene
+            .gaussians
+            .into_iter()
+            .zip(&self.splat_assets[SCENE_ASSET].gaussians)
+            .zip(groups)
+            .map(|((mut gaussian, edited), group)| {
+                gaussian.apply_edits(edited, sh_rotations[group as usize].as_ref());
+                gaussian
+            })
+            .enumerate()
+            .filter(|(index, gaussian)| {
+                self.edits.state(*index) != GaussianState::Deleted
+                    && !(self.apply_filters_on_save && self.edits.is_filtered(*index))
+                    && (!apply_crop
+                        || crop::keeps(&self.crop_volumes, Vec3::from(gaussian.position)))
+            })
+            .map(|(_, gaussian)| gaussian)
+            .collect();
+        ply::PlyScene {
+            gaussians,
+            sh_degree: scene.sh_degree,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_ply(
+        &self,
+        output: &std::path::Path,
+        apply_crop: bool,
+        decimate_target: Option<usize>,
+    ) -> String {
+        let mut scene = self.edited_scene(apply_crop);
+        if let Some(target) = decimate_target {
+            scene.gaussians = decimate::decimate(scene.gaussians, &self.training_cameras, target);
+        }
+        if self.bake_scene_transform {
+            scene_transform::bake_scene_transform(&self.scene_transform, &mut scene.gaussians);
+        }
+        match ply::save_ply(output, &scene.gaussians, scene.sh_degree) {
+            Ok(()) => format!(
+                "Wrote {} gaussians to {}",
+                scene.gaussians.len(),
+                output.display()
+            ),
+            Err(error) => format!("Failed to write {}: {}", output.display(), error),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn merge_objects(&self, world: &World) -> String {
+        let inputs = self
+            .splat_objects
+            .iter()
+            .filter(|(entity, _)| {
+                world
+                    .get_visibility(*entity)
+                    .is_none_or(|visibility| visibility.visible)
+            })
+            .map(|(entity, splat)| {
+                let transform = world.get_local_transform(*entity).map_or_else(
+                    SceneTransform::default,
+                    |local_transform| SceneTransform {
+                        rotation: local_transform.rotation.coords.into(),
+                        translation: local_transform.translation.into(),
+                        scale: local_transform.scale.x,
+                    },
+                );
+                if splat.asset == SCENE_ASSET {
+                    let scene = self.edited_scene(false);
+                    merge::MergeInput {
+                        gaussians: scene.gaussians,
+                        sh_degree: scene.sh_degree,
+                        transform,
+                    }
+                } else {
+                    merge::MergeInput {
+                        transform,
+                        ..merge::MergeInput::load(&self.splat_assets[splat.asset].path)
+                    }
+                }
+            })
+            .collect();
+        let settings = merge::MergeSettings {
+            sh_degree: self.merge_sh_degree,
+            dedupe_distance: self
+                .remove_merge_duplicates
+                .then_some(self.merge_dedupe_distance),
+        };
+        let output = merge::merged_path(&self.ply_file);
+        let merged = merge::merge_scenes(inputs, settings);
+        match merged.save(&output) {
+            Ok(()) => format!(
+                "Merged {} gaussians (SH degree {}, {} duplicates removed) into {}",
+                merged.gaussians.len(),
+                merged.sh_degree,
+                merged.duplicates_removed,
+                output.display()
+            ),
+            Err(error) => format!("Failed to write {}: {}", output.display(), error),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_streaming(
+        &mut self,
+        device: &wgpu::Device,
+        mut gaussians: Vec<GpuGaussian>,
+    ) -> Vec<GpuGaussian> {
+        let Some(chunk_file) = self.chunk_file.take() else {
+            return gaussians;
+        };
+        let buffer_gaussians = device.limits().max_storage_buffer_binding_size as usize
+            / std::mem::size_of::<GpuGaussian>();
+        let max_gaussians = (1usize << buffer_gaussians.ilog2()).saturating_sub(gaussians.len());
+        let streamer =
+            streaming::ChunkStreamer::new(chunk_file, self.gpu_budget_mb << 20, max_gaussians);
+        self.stream_offset = gaussians.len();
+        gaussians.resize(
+            gaussians.len() + streamer.capacity(),
+            streaming::empty_gaussian(),
+        );
+        self.streamer = Some(streamer);
+        gaussians
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_streaming(&mut self, splat_pass: &mut SplatPass, world: &World, model: Mat4) {
+        let Some(streamer) = &mut self.streamer else {
+            return;
+        };
+        let Some(camera_matrices) = query_active_camera_matrices(world) else {
+            return;
+        };
+        let camera_to_model = model.try_inverse().unwrap_or_else(Mat4::identity)
+            * camera_matrices
+                .view
+                .try_inverse()
+                .unwrap_or_else(Mat4::identity);
+        for (first, gaussians) in streamer.update(camera_to_model.column(3).xyz()) {
+            splat_pass.update_gaussians(self.stream_offset + first, &gaussians);
+        }
+        self.streaming_stats = Some(streamer.stats());
+    }
+}
+
+fn project_to_screen(view_projection: &Mat4, viewport: (u32, u32), point: Vec3) -> Option<Vec2> {
+    let (width, height) = viewport;
+    let clip = view_projection * point.push(1.0);
+    (clip.w > 0.0).then(|| {
+        Vec2::new(
+            (clip.x / clip.w * 0.5 + 0.5) * width as f32,
+            (0.5 - clip.y / clip.w * 0.5) * height as f32,
+        )
+    })
+}
+
+fn pointer_captured(world: &World) -> bool {
+    world
+        .resources
+        .user_interface
+        .state
+        .as_ref()
+        .is_some_and(|gui_state| gui_state.egui_ctx().wants_pointer_input())
+}
+
+fn active_camera_transform(world: &World) -> Option<(Vec3, Quat)> {
+    let local_transform = world.get_local_transform(world.resources.active_camera?)?;
+    Some((local_transform.translation, local_transform.rotation))
+}
+
+fn restore_orbit_camera(world: &mut World, y_fov_rad: f32) {
+    let Some(entity) = world.resources.active_camera else {
+        return;
+    };
+
+    set_perspective_projection(world, entity, y_fov_rad);
+
+    let Some(pan_orbit) = world.get_pan_orbit_camera_mut(entity) else {
+        return;
+    };
+    pan_orbit.enabled = true;
+    let (position, rotation) = nightshade::ecs::camera::compute_pan_orbit_transform(
+        pan_orbit.focus,
+        pan_orbit.yaw,
+        pan_orbit.pitch,
+        pan_orbit.radius,
+    );
+    if let Some(local_transform) = world.get_local_transform_mut(entity) {
+        local_transform.translation = position;
+        local_transform.rotation = rotation;
+    }
+    mark_local_transform_dirty(world, entity);
+}
+
+fn set_perspective_projection(world: &mut World, entity: Entity, y_fov_rad: f32) {
+    if let Some(camera) = world.get_camera_mut(entity) {
+        camera.projection = Projection::Perspective(PerspectiveCamera {
+            y_fov_rad,
+            ..Default::default()
+        });
+    }
+}
+
+fn set_camera_view(world: &mut World, position: Vec3, rotation: Quat, y_fov_rad: f32) {
+    let Some(entity) = world.resources.active_camera else {
+        return;
+    };
+
+    if let Some(pan_orbit) = world.get_pan_orbit_camera_mut(entity) {
+        pan_orbit.enabled = false;
+    }
+    set_perspective_projection(world, entity, y_fov_rad);
+    if let Some(local_transform) = world.get_local_transform_mut(entity) {
+        local_transform.translation = position;
+        local_transform.rotation = rotation;
+    }
+    mark_local_transform_dirty(world, entity);
+}
+
+fn current_camera_pose(world: &World) -> Option<CameraPose> {
+    let entity = world.resources.active_camera?;
+    let matrices = nightshade::ecs::camera::queries::query_active_camera_matrices(world)?;
+    let position = matrices.camera_position;
+    let forward = -Vec3::new(
+        matrices.view[(2, 0)],
+        matrices.view[(2, 1)],
+        matrices.view[(2, 2)],
+    );
+    let target = world
+        .get_pan_orbit_camera(entity)
+        .filter(|pan_orbit| pan_orbit.enabled)
+        .map_or(position + forward, |pan_orbit| pan_orbit.focus);
+    Some(CameraPose {
+        position,
+        target,
+        y_fov_rad: active_camera_y_fov(world),
+    })
+}
+
+fn active_camera_y_fov(world: &World) -> f32 {
+    let camera = world
+        .resources
+        .active_camera
+        .and_then(|entity| world.get_camera(entity));
+    match camera.map(|camera| &camera.projection) {
+        Some(Projection::Perspective(perspective)) => perspective.y_fov_rad,
+        _ => PerspectiveCamera::default().y_fov_rad,
+    }
+}
+
+fn apply_camera_pose(world: &mut World, pose: &CameraPose) {
+    let rotation = nalgebra_glm::quat_conjugate(&nalgebra_glm::to_quat(&pose.view_matrix()));
+    set_camera_view(world, pose.position, rotation, pose.y_fov_rad);
 }
 
 impl State for GaussianSplatViewer {
@@ -80,15 +1718,17 @@ impl State for GaussianSplatViewer {
         world.resources.graphics.show_grid = false;
         world.resources.graphics.atmosphere = Atmosphere::None;
 
+        let (focus, radius) = orbit_framing(self.scene_bounds.as_ref(), &self.scene_transform);
         let camera_entity = spawn_pan_orbit_camera(
             world,
-            Vec3::new(0.0, 0.0, 0.0),
-            5.0,
-            0.0,
-            std::f32::consts::FRAC_PI_4,
+            focus,
+            radius,
+            INITIAL_CAMERA_YAW,
+            INITIAL_CAMERA_PITCH,
             "Main Camera".to_string(),
         );
         world.resources.active_camera = Some(camera_entity);
+        self.splat_objects = splat_object::spawn_splat_objects(world, &self.splat_assets);
     }
 
     fn configure_render_graph(
@@ -98,9 +1738,14 @@ impl State for GaussianSplatViewer {
         surface_format: wgpu::TextureFormat,
         resources: RenderResources,
     ) {
-        let gaussians = GAUSSIANS.get().expect("Gaussians not loaded");
-
-        let splat_pass = SplatPass::new(device, gaussians, wgpu::TextureFormat::Rgba16Float);
+        let gaussians = splat_object::combined_gaussians(&self.splat_assets);
+        #[cfg(not(target_arch = "wasm32"))]
+        let gaussians = self.start_streaming(device, gaussians);
+        #[cfg(not(target_arch = "wasm32"))]
+        let lod = self.streamer.is_none().then(|| LodTree::build(&gaussians));
+        #[cfg(target_arch = "wasm32")]
+        let lod = Some(LodTree::build(&gaussians));
+        let splat_pass = SplatPass::new(device, &gaussians, lod, wgpu::TextureFormat::Rgba16Float);
 
         graph
             .pass(Box::new(splat_pass))
@@ -114,22 +1759,181 @@ impl State for GaussianSplatViewer {
             .write("output", resources.swapchain);
     }
 
+    fn update_render_graph(&mut self, graph: &mut RenderGraph<World>, world: &World) {
+        if let Some(splat_pass) = graph.get_pass_mut("splat_pass")
+            && let Some(splat_pass) =
+                (splat_pass as &mut dyn std::any::Any).downcast_mut::<SplatPass>()
+        {
+            splat_pass.set_settings(self.splat_settings);
+            if self.splat_settings.filter_mode == FilterMode::MipSplatting
+                && !self.filter_3d_applied
+            {
+                self.apply_filter_3d(splat_pass);
+            }
+            let objects = splat_object::gpu_objects(world, &self.splat_objects);
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(scene_object) = objects.first() {
+                self.update_streaming(splat_pass, world, Mat4::from(scene_object.model));
+            }
+            splat_pass.set_objects(objects);
+            splat_pass
+                .set_crop_volumes(crop::gpu_volumes(&self.crop_volumes, &self.scene_transform));
+            if let Some(words) = self.selection.take_dirty() {
+                splat_pass.set_selection(words);
+            }
+            if let Some(words) = self.edits.take_dirty() {
+                splat_pass.set_inactive(&words);
+            }
+            if let Some(range) = self.dirty_gaussians.take() {
+                splat_pass.update_gaussians(
+                    range.start,
+                    &self.splat_assets[SCENE_ASSET].gaussians[range],
+                );
+            }
+            self.overdraw_stats = splat_pass.overdraw_stats();
+            self.lod_splat_count = splat_pass.lod_splat_count();
+            self.gpu_timings_supported = splat_pass.timings_supported();
+            self.gpu_timings = splat_pass.stage_timings();
+        }
+    }
+
     fn run_systems(&mut self, world: &mut World) {
-        pan_orbit_camera_system(world);
+        self.update_scene_object(world);
+        self.update_frusta(world);
+        self.update_crop_lines(world);
+        self.update_gizmo_lines(world);
+        self.update_filter();
+
+        if self.training_view.is_some() {
+            return;
+        }
+
+        if let Some(transition) = &mut self.bookmark_transition {
+            let (position, rotation, y_fov_rad) =
+                transition.advance(world.resources.window.timing.delta_time);
+            let finished = transition.finished();
+            set_camera_view(world, position, rotation, y_fov_rad);
+            if finished {
+                self.bookmark_transition = None;
+                self.y_fov_rad = y_fov_rad;
+                if self.fly_camera.is_some() {
+                    self.enter_fly_mode(world);
+                } else {
+                    self.enter_orbit_mode(world);
+                }
+            }
+            return;
+        }
+
+        let Some(time) = self.camera_path_preview_time.as_mut() else {
+            if !self.update_crop_drag(world)
+                && !self.update_gizmo_drag(world)
+                && !self.update_selection(world)
+            {
+                match &mut self.fly_camera {
+                    Some(fly_camera) => fly_camera.update(world),
+                    None => pan_orbit_camera_system(world),
+                }
+            }
+            self.apply_navigation_projection(world);
+            return;
+        };
+
+        *time += world.resources.window.timing.delta_time;
+        if let Some((start, end)) = self.camera_path.time_range()
+            && *time > end
+        {
+            *time = start;
+        }
+        if let Some(pose) = self.camera_path.sample(*time) {
+            apply_camera_pose(world, &pose);
+        }
     }
 
     fn ui(&mut self, world: &mut World, ui_context: &egui::Context) {
+        self.selection_overlay(ui_context);
         egui::Window::new("Gaussian Splatting").show(ui_context, |ui| {
-            ui.label(format!("Gaussians: {}", self.gaussian_count));
-
-            let fps = 1.0 / world.resources.window.timing.delta_time.max(0.001);
-            ui.label(format!("FPS: {:.1}", fps));
+            self.navigation_panel(ui, world);
+            self.timings_panel(ui, world);
+            ui.separator();
+            self.anti_aliasing_panel(ui);
+            ui.separator();
+            self.lod_panel(ui);
+            ui.separator();
+            self.depth_panel(ui);
+            ui.separator();
+            self.render_mode_panel(ui);
+            ui.separator();
+            self.scene_orientation_panel(ui, world);
+            self.objects_panel(ui, world);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.merge_panel(ui, world);
+            ui.separator();
+            self.crop_panel(ui);
+            ui.separator();
+            self.selection_panel(ui);
+            self.edit_panel(ui);
+            ui.separator();
+            self.filter_panel(ui);
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+                self.decimate_panel(ui);
+            }
+            ui.separator();
+            self.camera_path_panel(ui, world);
+            ui.separator();
+            self.bookmark_panel(ui, world);
+            self.training_view_panel(ui, world);
         });
     }
 
     fn on_keyboard_input(&mut self, world: &mut World, key_code: KeyCode, key_state: KeyState) {
-        if matches!((key_code, key_state), (KeyCode::KeyQ, KeyState::Pressed)) {
-            world.resources.window.should_exit = true;
+        if key_state != KeyState::Pressed {
+            return;
+        }
+        if world
+            .resources
+            .user_interface
+            .state
+            .as_ref()
+            .is_some_and(|gui_state| gui_state.egui_ctx().wants_keyboard_input())
+        {
+            return;
+        }
+        if let Some(index) = BOOKMARK_KEYS.iter().position(|key| *key == key_code) {
+            self.recall_bookmark(world, index);
+            return;
+        }
+        let keyboard = &world.resources.input.keyboard;
+        let control = keyboard.is_key_pressed(KeyCode::ControlLeft)
+            || keyboard.is_key_pressed(KeyCode::ControlRight);
+        let shift = keyboard.is_key_pressed(KeyCode::ShiftLeft)
+            || keyboard.is_key_pressed(KeyCode::ShiftRight);
+        match key_code {
+            KeyCode::KeyZ if control && shift => self.redo(),
+            KeyCode::KeyZ if control => self.undo(),
+            KeyCode::KeyY if control => self.redo(),
+            KeyCode::Delete => self.edit_selection("Delete", GaussianState::Deleted),
+            KeyCode::KeyH if shift => self.unhide_all(),
+            KeyCode::KeyH => self.edit_selection("Hide", GaussianState::Hidden),
+            KeyCode::KeyI => self.isolate_selection(),
+            KeyCode::KeyQ => world.resources.window.should_exit = true,
+            KeyCode::KeyF => self.frame_scene(world),
+            KeyCode::KeyV => self.set_fly_mode(world, self.fly_camera.is_none()),
+            KeyCode::KeyO => {
+                let projection_mode = match self.projection_mode {
+                    ProjectionMode::Perspective => ProjectionMode::Orthographic,
+                    ProjectionMode::Orthographic => ProjectionMode::Perspective,
+                };
+                self.set_projection_mode(world, projection_mode);
+            }
+            KeyCode::PageDown => self.step_training_view(world, 1),
+            KeyCode::PageUp => self.step_training_view(world, -1),
+            KeyCode::Escape if self.training_view.is_some() => {
+                self.select_training_view(world, None)
+            }
+            _ => {}
         }
     }
 }
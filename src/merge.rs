@@ -0,0 +1,240 @@
+use crate::gaussian::RawGaussian;
+use crate::ply::{self, MAX_SH_DEGREE};
+use crate::scene_transform::{self, SceneTransform};
+use nightshade::prelude::*;
+use std::collections::HashMap;
+
+pub struct MergeInput {
+    pub gaussians: Vec<RawGaussian>,
+    pub sh_degree: usize,
+    pub transform: SceneTransform,
+}
+
+#[derive(Copy, Clone)]
+pub struct MergeSettings {
+    pub sh_degree: Option<usize>,
+    pub dedupe_distance: Option<f32>,
+}
+
+pub struct MergedScene {
+    pub gaussians: Vec<RawGaussian>,
+    pub sh_degree: usize,
+    pub duplicates_removed: usize,
+}
+
+impl MergeInput {
+    pub fn load(path: &std::path::Path) -> Self {
+        let scene = ply::load_ply_scene(path);
+        let transform =
+            scene_transform::load_scene_transform(&scene_transform::transform_path(path))
+                .unwrap_or_default();
+        Self {
+            gaussians: scene.gaussians,
+            sh_degree: scene.sh_degree,
+            transform,
+        }
+    }
+}
+
+impl MergedScene {
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        ply::save_ply(path, &self.gaussians, self.sh_degree)
+    }
+}
+
+pub fn merged_path(ply_path: &std::path::Path) -> std::path::PathBuf {
+    ply_path.with_extension("merged.ply")
+}
+
+pub fn merge_scenes(inputs: Vec<MergeInput>, settings: MergeSettings) -> MergedScene {
+    let sh_degree = settings
+        .sh_degree
+        .unwrap_or_else(|| {
+            inputs
+                .iter()
+                .map(|input| input.sh_degree)
+                .max()
+                .unwrap_or(0)
+        })
+        .min(MAX_SH_DEGREE);
+
+    let mut gaussians = Vec::new();
+    let mut sources = Vec::new();
+    for (source, mut input) in inputs.into_iter().enumerate() {
+        if input.transform != SceneTransform::default() {
+            scene_transform::bake_scene_transform(&input.transform, &mut input.gaussians);
+        }
+        for gaussian in &mut input.gaussians {
+            gaussian.truncate_sh(sh_degree);
+        }
+        sources.extend(std::iter::repeat_n(source, input.gaussians.len()));
+        gaussians.extend(input.gaussians);
+    }
+
+    let merged_count = gaussians.len();
+    if let Some(distance) = settings.dedupe_distance.filter(|distance| *distance > 0.0) {
+        gaussians = remove_duplicates(gaussians, &sources, distance);
+    }
+    MergedScene {
+        duplicates_removed: merged_count - gaussians.len(),
+        gaussians,
+        sh_degree,
+    }
+}
+
+fn remove_duplicates(
+    gaussians: Vec<RawGaussian>,
+    sources: &[usize],
+    distance: f32,
+) -> Vec<RawGaussian> {
+    let cell = |position: &Vec3| position.map(|value| (value / distance).floor() as i32);
+    let mut grid: HashMap<[i32; 3], Vec<usize>> = HashMap::new();
+    let mut kept = Vec::with_capacity(gaussians.len());
+    for (index, gaussian) in gaussians.iter().enumerate() {
+        let position = Vec3::from(gaussian.position);
+        let center = cell(&position);
+        let duplicate = (-1..=1).any(|x| {
+            (-1..=1).any(|y| {
+                (-1..=1).any(|z| {
+                    grid.get(&[center.x + x, center.y + y, center.z + z])
+                        .is_some_and(|neighbors| {
+                            neighbors.iter().any(|&neighbor| {
+                                sources[neighbor] != sources[index]
+                                    && (Vec3::from(gaussians[neighbor].position) - position)
+                                        .norm_squared()
+                                        <= distance * distance
+                            })
+                        })
+                })
+            })
+        });
+        if !duplicate {
+            grid.entry([center.x, center.y, center.z])
+                .or_default()
+                .push(index);
+            kept.push(*gaussian);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sh_rotation::SH_COEFFICIENTS_PER_CHANNEL;
+
+    fn raw_gaussian(position: [f32; 3]) -> RawGaussian {
+        RawGaussian {
+            position,
+            sh_rest: std::array::from_fn(|index| index as f32 + 1.0),
+            scale: [-3.0, -2.0, -1.0],
+            rotation: [1.0, 0.0, 0.0, 0.0],
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    fn input(positions: &[[f32; 3]], sh_degree: usize) -> MergeInput {
+        let mut gaussians: Vec<RawGaussian> = positions.iter().copied().map(raw_gaussian).collect();
+        for gaussian in &mut gaussians {
+            gaussian.truncate_sh(sh_degree);
+        }
+        MergeInput {
+            gaussians,
+            sh_degree,
+            transform: SceneTransform::default(),
+        }
+    }
+
+    fn settings(sh_degree: Option<usize>, dedupe_distance: Option<f32>) -> MergeSettings {
+        MergeSettings {
+            sh_degree,
+            dedupe_distance,
+        }
+    }
+
+    fn coefficient_count(gaussian: &RawGaussian) -> usize {
+        gaussian.sh_rest[..SH_COEFFICIENTS_PER_CHANNEL]
+            .iter()
+            .rposition(|coefficient| *coefficient != 0.0)
+            .map_or(0, |last| last + 1)
+    }
+
+    #[test]
+    fn sh_degree_defaults_to_the_highest_input() {
+        let merged = merge_scenes(
+            vec![input(&[[0.0; 3]], 1), input(&[[1.0; 3]], 3)],
+            settings(None, None),
+        );
+        assert_eq!(merged.sh_degree, 3);
+        assert_eq!(coefficient_count(&merged.gaussians[0]), 3);
+        assert_eq!(coefficient_count(&merged.gaussians[1]), 15);
+    }
+
+    #[test]
+    fn sh_degree_truncates_every_channel() {
+        let merged = merge_scenes(
+            vec![input(&[[0.0; 3]], 3), input(&[[1.0; 3]], 2)],
+            settings(Some(1), None),
+        );
+        assert_eq!(merged.sh_degree, 1);
+        for gaussian in &merged.gaussians {
+            for channel in gaussian.sh_rest.chunks_exact(SH_COEFFICIENTS_PER_CHANNEL) {
+                assert!(channel[..3].iter().all(|coefficient| *coefficient != 0.0));
+                assert!(channel[3..].iter().all(|coefficient| *coefficient == 0.0));
+            }
+        }
+        let clamped = merge_scenes(vec![input(&[[0.0; 3]], 3)], settings(Some(7), None));
+        assert_eq!(clamped.sh_degree, MAX_SH_DEGREE);
+    }
+
+    #[test]
+    fn transform_sidecar_is_baked_into_the_input() {
+        let directory = std::env::temp_dir().join(format!("merge_sidecar_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("Failed to create test directory");
+        let path = directory.join("capture.ply");
+        let gaussians = [raw_gaussian([1.0, 0.0, 0.0])];
+        ply::save_ply(&path, &gaussians, 3).expect("Failed to save test PLY");
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let transform = SceneTransform {
+            rotation: [0.0, half, 0.0, half],
+            translation: [0.0, 5.0, 0.0],
+            scale: 2.0,
+        };
+        scene_transform::save_scene_transform(&scene_transform::transform_path(&path), &transform)
+            .expect("Failed to save test transform");
+
+        let loaded = MergeInput::load(&path);
+        std::fs::remove_dir_all(&directory).ok();
+        assert!(loaded.transform == transform);
+
+        let merged = merge_scenes(vec![loaded], settings(None, None));
+        let gaussian = merged.gaussians[0];
+        let expected_position = Vec3::new(0.0, 5.0, -2.0);
+        assert!((Vec3::from(gaussian.position) - expected_position).norm() < 1e-5);
+        let expected_scale = Vec3::from(gaussians[0].scale).add_scalar(2.0_f32.ln());
+        assert!((Vec3::from(gaussian.scale) - expected_scale).norm() < 1e-5);
+        let [w, x, y, z] = gaussian.rotation;
+        assert!((Vec4::new(w, x, y, z) - Vec4::new(half, 0.0, half, 0.0)).norm() < 1e-5);
+        assert!(gaussian.sh_rest != gaussians[0].sh_rest);
+    }
+
+    #[test]
+    fn dedupe_only_drops_overlap_between_inputs() {
+        let first = input(&[[0.0, 0.0, 0.0], [0.05, 0.0, 0.0], [0.99, 0.0, 0.0]], 0);
+        let second = input(&[[0.03, 0.0, 0.0], [1.02, 0.0, 0.0], [0.5, 0.0, 0.0]], 0);
+        let merged = merge_scenes(vec![first, second], settings(None, Some(0.1)));
+        let positions: Vec<f32> = merged
+            .gaussians
+            .iter()
+            .map(|gaussian| gaussian.position[0])
+            .collect();
+        assert_eq!(positions, [0.0, 0.05, 0.99, 0.5]);
+        assert_eq!(merged.duplicates_removed, 2);
+
+        let first = input(&[[0.0; 3]], 0);
+        let second = input(&[[0.03, 0.0, 0.0]], 0);
+        let kept = merge_scenes(vec![first, second], settings(None, None));
+        assert_eq!(kept.gaussians.len(), 2);
+        assert_eq!(kept.duplicates_removed, 0);
+    }
+}
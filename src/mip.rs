@@ -0,0 +1,54 @@
+use crate::cameras::TrainingCamera;
+use crate::gaussian::GpuGaussian;
+
+const FILTER_3D_VARIANCE: f32 = 0.2;
+const MIN_CAMERA_DEPTH: f32 = 0.2;
+const SCREEN_MARGIN: f32 = 0.15;
+
+pub fn apply_filter_3d(gaussians: &mut [GpuGaussian], cameras: &[TrainingCamera]) {
+    if cameras.is_empty() {
+        return;
+    }
+
+    let mut sampling_intervals = vec![f32::INFINITY; gaussians.len()];
+
+    for camera in cameras {
+        let focal = camera.fx.max(camera.fy);
+        let width = camera.width as f32;
+        let height = camera.height as f32;
+
+        for (gaussian, interval) in gaussians.iter().zip(sampling_intervals.iter_mut()) {
+            let [x, y, z] = camera.world_to_camera(gaussian.position);
+            if z <= MIN_CAMERA_DEPTH {
+                continue;
+            }
+
+            let screen_x = x / z * camera.fx + width * 0.5;
+            let screen_y = y / z * camera.fy + height * 0.5;
+            let in_screen = screen_x >= -SCREEN_MARGIN * width
+                && screen_x <= (1.0 + SCREEN_MARGIN) * width
+                && screen_y >= -SCREEN_MARGIN * height
+                && screen_y <= (1.0 + SCREEN_MARGIN) * height;
+            if !in_screen {
+                continue;
+            }
+
+            *interval = interval.min(z / focal);
+        }
+    }
+
+    let largest_seen_interval = sampling_intervals
+        .iter()
+        .copied()
+        .filter(|interval| interval.is_finite())
+        .fold(0.0f32, f32::max);
+
+    for (gaussian, interval) in gaussians.iter_mut().zip(sampling_intervals) {
+        let interval = if interval.is_finite() {
+            interval
+        } else {
+            largest_seen_interval
+        };
+        gaussian.filter_variance = interval * interval * FILTER_3D_VARIANCE;
+    }
+}
@@ -0,0 +1,327 @@
+use crate::profiling::{GpuProfiler, Stage};
+use crate::readback::Readback;
+use crate::splat_pass::{RenderPipelineKind, buffer_layout_entry, create_render_pipeline};
+use nightshade::prelude::wgpu;
+
+const OVERDRAW_SHADER: &str = include_str!("shaders/overdraw.wgsl");
+
+const FRAGMENT_COUNTS_HEADER_SIZE: u64 = 16;
+const VISIBILITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OverdrawStats {
+    pub total_fragments: u32,
+    pub saturated_fragments: u32,
+    pub max_fragments: u32,
+    pub covered_pixels: u32,
+}
+
+pub struct Overdraw {
+    fragment_count_bind_group_layout: wgpu::BindGroupLayout,
+    heatmap_bind_group_layout: wgpu::BindGroupLayout,
+    count_pipeline: wgpu::RenderPipeline,
+    heatmap_pipeline: wgpu::RenderPipeline,
+    stats_buffer: wgpu::Buffer,
+    stats_readback: Readback,
+    latest_stats: Option<OverdrawStats>,
+    target: Option<OverdrawTarget>,
+}
+
+struct OverdrawTarget {
+    width: u32,
+    height: u32,
+    counts_buffer: wgpu::Buffer,
+    visibility_view: wgpu::TextureView,
+    fragment_count_bind_group: wgpu::BindGroup,
+    heatmap_bind_group: wgpu::BindGroup,
+}
+
+impl Overdraw {
+    pub fn new(
+        device: &wgpu::Device,
+        render_bind_group_layout: &wgpu::BindGroupLayout,
+        render_shader: &wgpu::ShaderModule,
+        color_format: wgpu::TextureFormat,
+    ) -> Self {
+        let fragment_count_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Fragment Count Bind Group Layout"),
+                entries: &[buffer_layout_entry(
+                    0,
+                    wgpu::ShaderStages::FRAGMENT,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                    false,
+                )],
+            });
+
+        let count_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Fragment Count Pipeline Layout"),
+                bind_group_layouts: &[render_bind_group_layout, &fragment_count_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let count_pipeline = create_render_pipeline(
+            device,
+            &count_pipeline_layout,
+            render_shader,
+            VISIBILITY_FORMAT,
+            RenderPipelineKind::FragmentCount,
+        );
+
+        let heatmap_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Heatmap Bind Group Layout"),
+                entries: &[
+                    buffer_layout_entry(
+                        0,
+                        wgpu::ShaderStages::FRAGMENT,
+                        wgpu::BufferBindingType::Uniform,
+                        false,
+                    ),
+                    buffer_layout_entry(
+                        1,
+                        wgpu::ShaderStages::FRAGMENT,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                        false,
+                    ),
+                    buffer_layout_entry(
+                        2,
+                        wgpu::ShaderStages::FRAGMENT,
+                        wgpu::BufferBindingType::Storage { read_only: false },
+                        false,
+                    ),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let overdraw_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overdraw Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(OVERDRAW_SHADER)),
+        });
+
+        let heatmap_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Heatmap Pipeline Layout"),
+                bind_group_layouts: &[&heatmap_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let heatmap_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Heatmap Pipeline"),
+            layout: Some(&heatmap_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &overdraw_shader,
+                entry_point: Some("vertex_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &overdraw_shader,
+                entry_point: Some("fragment_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let stats_size = std::mem::size_of::<OverdrawStats>() as u64;
+        let stats_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overdraw Stats Buffer"),
+            size: stats_size,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            fragment_count_bind_group_layout,
+            heatmap_bind_group_layout,
+            count_pipeline,
+            heatmap_pipeline,
+            stats_buffer,
+            stats_readback: Readback::new(device, "Overdraw Stats Readback Buffer", stats_size),
+            latest_stats: None,
+            target: None,
+        }
+    }
+
+    pub fn stats(&self) -> Option<OverdrawStats> {
+        self.latest_stats
+    }
+
+    pub fn poll_stats(&mut self) {
+        if let Some(data) = self.stats_readback.poll() {
+            self.latest_stats = Some(bytemuck::pod_read_unaligned(&data));
+        }
+    }
+
+    pub fn ensure_target(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        uniform_buffer: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+    ) {
+        if self
+            .target
+            .as_ref()
+            .is_some_and(|target| target.width == width && target.height == height)
+        {
+            return;
+        }
+
+        let counts_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Fragment Counts Buffer"),
+            size: FRAGMENT_COUNTS_HEADER_SIZE + 4 * width as u64 * height as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(
+            &counts_buffer,
+            0,
+            bytemuck::cast_slice(&[width, height, 0, 0]),
+        );
+
+        let visibility_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Overdraw Visibility Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: VISIBILITY_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let visibility_view = visibility_texture.create_view(&Default::default());
+
+        let fragment_count_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Fragment Count Bind Group"),
+            layout: &self.fragment_count_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: counts_buffer.as_entire_binding(),
+            }],
+        });
+
+        let heatmap_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Heatmap Bind Group"),
+            layout: &self.heatmap_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: counts_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.stats_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&visibility_view),
+                },
+            ],
+        });
+
+        self.target = Some(OverdrawTarget {
+            width,
+            height,
+            counts_buffer,
+            visibility_view,
+            fragment_count_bind_group,
+            heatmap_bind_group,
+        });
+    }
+
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        render_bind_group: &wgpu::BindGroup,
+        draw_indirect_buffer: &wgpu::Buffer,
+        color_view: &wgpu::TextureView,
+        color_ops: wgpu::Operations<wgpu::Color>,
+        profiler: Option<&GpuProfiler>,
+    ) {
+        let Some(target) = &self.target else {
+            return;
+        };
+
+        encoder.clear_buffer(&target.counts_buffer, FRAGMENT_COUNTS_HEADER_SIZE, None);
+        encoder.clear_buffer(&self.stats_buffer, 0, None);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Fragment Count Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.visibility_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: profiler
+                    .map(|profiler| profiler.render_timestamp_writes(Stage::Render, true, false)),
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.count_pipeline);
+            render_pass.set_bind_group(0, render_bind_group, &[]);
+            render_pass.set_bind_group(1, &target.fragment_count_bind_group, &[]);
+            render_pass.draw_indirect(draw_indirect_buffer, 0);
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Heatmap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: color_view,
+                    resolve_target: None,
+                    ops: color_ops,
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: profiler
+                    .map(|profiler| profiler.render_timestamp_writes(Stage::Render, false, true)),
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.heatmap_pipeline);
+            render_pass.set_bind_group(0, &target.heatmap_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        self.stats_readback
+            .copy_from_buffer(encoder, &self.stats_buffer);
+    }
+}
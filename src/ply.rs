@@ -1,16 +1,29 @@
 use crate::gaussian::RawGaussian;
 
-pub fn load_ply_from_bytes(data: &[u8]) -> Vec<RawGaussian> {
+pub const MAX_SH_DEGREE: usize = 3;
+
+pub struct PlyScene {
+    pub gaussians: Vec<RawGaussian>,
+    pub sh_degree: usize,
+}
+
+pub fn sh_rest_count(sh_degree: usize) -> usize {
+    (sh_degree + 1) * (sh_degree + 1) - 1
+}
+
+pub fn parse_ply(data: &[u8]) -> PlyScene {
     let (header_end, line_ending_len) = find_header_end(data);
     let header_str =
         std::str::from_utf8(&data[..header_end]).expect("PLY header is not valid UTF-8");
 
     let vertex_count = parse_vertex_count(header_str);
+    let properties = parse_properties(header_str);
 
     let body_start = header_end + b"end_header".len() + line_ending_len;
     let body = &data[body_start..];
 
-    let expected_size = vertex_count * std::mem::size_of::<RawGaussian>();
+    let stride = properties.len() * std::mem::size_of::<f32>();
+    let expected_size = vertex_count * stride;
     assert!(
         body.len() >= expected_size,
         "PLY body too small: expected at least {} bytes for {} vertices, got {}",
@@ -19,17 +32,113 @@ pub fn load_ply_from_bytes(data: &[u8]) -> Vec<RawGaussian> {
         body.len()
     );
 
-    let raw_slice: &[RawGaussian] = bytemuck::cast_slice(&body[..expected_size]);
+    if properties == property_names(MAX_SH_DEGREE) {
+        return PlyScene {
+            gaussians: bytemuck::pod_collect_to_vec(&body[..expected_size]),
+            sh_degree: MAX_SH_DEGREE,
+        };
+    }
+
+    let rest_per_channel = properties
+        .iter()
+        .filter(|name| name.starts_with("f_rest_"))
+        .count()
+        / 3;
+    let sh_degree = (0..=MAX_SH_DEGREE)
+        .find(|degree| sh_rest_count(*degree) == rest_per_channel)
+        .unwrap_or_else(|| {
+            panic!("PLY has {rest_per_channel} SH coefficients per channel, expected 0, 3, 8 or 15")
+        });
+
+    let targets: Vec<Option<usize>> = properties
+        .iter()
+        .map(|name| property_offset(name, rest_per_channel))
+        .collect();
+    let gaussians = body[..expected_size]
+        .chunks_exact(stride)
+        .map(|row| {
+            let mut values = [0.0f32; std::mem::size_of::<RawGaussian>() / 4];
+            for (target, bytes) in targets.iter().zip(row.chunks_exact(4)) {
+                if let Some(target) = target {
+                    values[*target] = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                }
+            }
+            bytemuck::cast(values)
+        })
+        .collect();
+    PlyScene {
+        gaussians,
+        sh_degree,
+    }
+}
 
-    raw_slice.to_vec()
+fn property_offset(name: &str, rest_per_channel: usize) -> Option<usize> {
+    if let Some(index) = name.strip_prefix("f_rest_") {
+        let index: usize = index.parse().ok()?;
+        let channel = index / rest_per_channel;
+        return Some(9 + channel * sh_rest_count(MAX_SH_DEGREE) + index % rest_per_channel);
+    }
+    property_names(MAX_SH_DEGREE)
+        .iter()
+        .position(|canonical| canonical == name)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub fn load_ply(path: &std::path::Path) -> Vec<RawGaussian> {
+pub fn load_ply_scene(path: &std::path::Path) -> PlyScene {
     let data = std::fs::read(path).unwrap_or_else(|error| {
         panic!("Failed to read PLY file {}: {}", path.display(), error);
     });
-    load_ply_from_bytes(&data)
+    parse_ply(&data)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_ply(
+    path: &std::path::Path,
+    gaussians: &[RawGaussian],
+    sh_degree: usize,
+) -> std::io::Result<()> {
+    let mut header = format!(
+        "ply\nformat binary_little_endian 1.0\nelement vertex {}\n",
+        gaussians.len()
+    );
+    for property in property_names(sh_degree) {
+        header.push_str(&format!("property float {property}\n"));
+    }
+    header.push_str("end_header\n");
+
+    let mut data = header.into_bytes();
+    if sh_degree == MAX_SH_DEGREE {
+        data.extend_from_slice(bytemuck::cast_slice(gaussians));
+    } else {
+        let rest_per_channel = sh_rest_count(sh_degree);
+        for gaussian in gaussians {
+            let mut values: Vec<f32> = Vec::with_capacity(62);
+            values.extend_from_slice(&gaussian.position);
+            values.extend_from_slice(&gaussian.normals);
+            values.extend_from_slice(&gaussian.sh_dc);
+            for channel in gaussian.sh_rest.chunks_exact(sh_rest_count(MAX_SH_DEGREE)) {
+                values.extend_from_slice(&channel[..rest_per_channel]);
+            }
+            values.push(gaussian.opacity);
+            values.extend_from_slice(&gaussian.scale);
+            values.extend_from_slice(&gaussian.rotation);
+            data.extend_from_slice(bytemuck::cast_slice(&values));
+        }
+    }
+    std::fs::write(path, data)
+}
+
+fn property_names(sh_degree: usize) -> Vec<String> {
+    let mut names: Vec<String> = ["x", "y", "z", "nx", "ny", "nz"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    names.extend((0..3).map(|index| format!("f_dc_{index}")));
+    names.extend((0..3 * sh_rest_count(sh_degree)).map(|index| format!("f_rest_{index}")));
+    names.push("opacity".to_string());
+    names.extend((0..3).map(|index| format!("scale_{index}")));
+    names.extend((0..4).map(|index| format!("rot_{index}")));
+    names
 }
 
 fn find_header_end(data: &[u8]) -> (usize, usize) {
@@ -53,6 +162,23 @@ fn find_header_end(data: &[u8]) -> (usize, usize) {
     panic!("Could not find 'end_header' in PLY file");
 }
 
+fn parse_properties(header: &str) -> Vec<String> {
+    header
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("property "))
+        .map(|property| {
+            let mut parts = property.split_whitespace();
+            let kind = parts.next().unwrap_or_default();
+            let name = parts.next().unwrap_or_default();
+            assert!(
+                kind == "float" || kind == "float32",
+                "Unsupported PLY property type '{kind}' for '{name}', only float properties are supported"
+            );
+            name.to_string()
+        })
+        .collect()
+}
+
 fn parse_vertex_count(header: &str) -> usize {
     for line in header.lines() {
         let trimmed = line.trim();
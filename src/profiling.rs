@@ -0,0 +1,159 @@
+use crate::readback::Readback;
+use nightshade::prelude::wgpu;
+use std::collections::VecDeque;
+
+const TIMING_HISTORY: usize = 60;
+
+#[derive(Copy, Clone)]
+pub enum Stage {
+    Clear,
+    Preprocess,
+    Sort,
+    Render,
+}
+
+const STAGE_COUNT: u32 = 4;
+const QUERY_COUNT: u32 = STAGE_COUNT * 2;
+
+#[derive(Copy, Clone, Default)]
+pub struct StageTimings {
+    pub clear_ms: f32,
+    pub preprocess_ms: f32,
+    pub sort_ms: f32,
+    pub render_ms: f32,
+}
+
+impl StageTimings {
+    pub fn total_ms(&self) -> f32 {
+        self.clear_ms + self.preprocess_ms + self.sort_ms + self.render_ms
+    }
+}
+
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: Readback,
+    history: VecDeque<StageTimings>,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &wgpu::Device) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Splat Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+
+        let size = QUERY_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Splat Timestamp Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback: Readback::new(device, "Splat Timestamp Readback Buffer", size),
+            history: VecDeque::with_capacity(TIMING_HISTORY),
+        })
+    }
+
+    pub fn compute_timestamp_writes(
+        &self,
+        stage: Stage,
+        begin: bool,
+        end: bool,
+    ) -> wgpu::ComputePassTimestampWrites<'_> {
+        let (beginning_of_pass_write_index, end_of_pass_write_index) =
+            self.write_indices(stage, begin, end);
+        wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index,
+            end_of_pass_write_index,
+        }
+    }
+
+    pub fn render_timestamp_writes(
+        &self,
+        stage: Stage,
+        begin: bool,
+        end: bool,
+    ) -> wgpu::RenderPassTimestampWrites<'_> {
+        let (beginning_of_pass_write_index, end_of_pass_write_index) =
+            self.write_indices(stage, begin, end);
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index,
+            end_of_pass_write_index,
+        }
+    }
+
+    fn write_indices(&self, stage: Stage, begin: bool, end: bool) -> (Option<u32>, Option<u32>) {
+        let base = stage as u32 * 2;
+        (begin.then_some(base), end.then_some(base + 1))
+    }
+
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.readback.resolve_query_set(
+            encoder,
+            &self.query_set,
+            QUERY_COUNT,
+            &self.resolve_buffer,
+        );
+    }
+
+    pub fn poll(&mut self, timestamp_period: f32) {
+        let Some(data) = self.readback.poll() else {
+            return;
+        };
+
+        let ticks: Vec<u64> = data
+            .chunks_exact(8)
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+        let milliseconds = |stage: Stage| {
+            let base = stage as usize * 2;
+            ticks[base + 1].saturating_sub(ticks[base]) as f64 * timestamp_period as f64 / 1.0e6
+        };
+
+        if self.history.len() == TIMING_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(StageTimings {
+            clear_ms: milliseconds(Stage::Clear) as f32,
+            preprocess_ms: milliseconds(Stage::Preprocess) as f32,
+            sort_ms: milliseconds(Stage::Sort) as f32,
+            render_ms: milliseconds(Stage::Render) as f32,
+        });
+    }
+
+    pub fn average(&self) -> Option<StageTimings> {
+        if self.history.is_empty() {
+            return None;
+        }
+
+        let count = self.history.len() as f32;
+        let sum = self
+            .history
+            .iter()
+            .fold(StageTimings::default(), |sum, timings| StageTimings {
+                clear_ms: sum.clear_ms + timings.clear_ms,
+                preprocess_ms: sum.preprocess_ms + timings.preprocess_ms,
+                sort_ms: sum.sort_ms + timings.sort_ms,
+                render_ms: sum.render_ms + timings.render_ms,
+            });
+
+        Some(StageTimings {
+            clear_ms: sum.clear_ms / count,
+            preprocess_ms: sum.preprocess_ms / count,
+            sort_ms: sum.sort_ms / count,
+            render_ms: sum.render_ms / count,
+        })
+    }
+}
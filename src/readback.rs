@@ -0,0 +1,82 @@
+use nightshade::prelude::wgpu;
+use std::sync::{Arc, Mutex};
+
+#[derive(Copy, Clone, PartialEq)]
+enum ReadbackState {
+    Idle,
+    Copied,
+    Mapping,
+    Mapped,
+}
+
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    state: Arc<Mutex<ReadbackState>>,
+}
+
+impl Readback {
+    pub fn new(device: &wgpu::Device, label: &str, size: u64) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            state: Arc::new(Mutex::new(ReadbackState::Idle)),
+        }
+    }
+
+    pub fn copy_from_buffer(&self, encoder: &mut wgpu::CommandEncoder, source: &wgpu::Buffer) {
+        let mut state = self.state.lock().unwrap();
+        if *state != ReadbackState::Idle {
+            return;
+        }
+        encoder.copy_buffer_to_buffer(source, 0, &self.buffer, 0, self.buffer.size());
+        *state = ReadbackState::Copied;
+    }
+
+    pub fn resolve_query_set(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        query_set: &wgpu::QuerySet,
+        query_count: u32,
+        resolve_buffer: &wgpu::Buffer,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if *state != ReadbackState::Idle {
+            return;
+        }
+        encoder.resolve_query_set(query_set, 0..query_count, resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(resolve_buffer, 0, &self.buffer, 0, self.buffer.size());
+        *state = ReadbackState::Copied;
+    }
+
+    pub fn poll(&self) -> Option<Vec<u8>> {
+        let current = *self.state.lock().unwrap();
+        match current {
+            ReadbackState::Copied => {
+                *self.state.lock().unwrap() = ReadbackState::Mapping;
+                let state = Arc::clone(&self.state);
+                self.buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        *state.lock().unwrap() = match result {
+                            Ok(()) => ReadbackState::Mapped,
+                            Err(_) => ReadbackState::Idle,
+                        };
+                    });
+                None
+            }
+            ReadbackState::Mapped => {
+                let data = self.buffer.slice(..).get_mapped_range().to_vec();
+                self.buffer.unmap();
+                *self.state.lock().unwrap() = ReadbackState::Idle;
+                Some(data)
+            }
+            ReadbackState::Idle | ReadbackState::Mapping => None,
+        }
+    }
+}
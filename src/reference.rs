@@ -0,0 +1,492 @@
+use crate::gaussian::GpuGaussian;
+use crate::splat_object::GpuSplatObject;
+use crate::splat_pass::FilterMode;
+use nightshade::prelude::nalgebra_glm::Mat3;
+use nightshade::prelude::*;
+
+const LEGACY_DILATION: f32 = 0.3;
+const MIP_KERNEL_VARIANCE: f32 = 0.1;
+const SH_C0: f32 = 0.282_094_8;
+
+struct ReferenceSplat {
+    color: Vec3,
+    conic: Vec3,
+    opacity: f32,
+    center: Vec2,
+    major_axis: Vec2,
+    minor_axis: Vec2,
+    depth_key: u32,
+}
+
+pub fn render_reference(
+    gaussians: &[GpuGaussian],
+    objects: &[GpuSplatObject],
+    view: &Mat4,
+    projection: &Mat4,
+    width: u32,
+    height: u32,
+    filter_mode: FilterMode,
+) -> image::Rgba32FImage {
+    let viewport = Vec2::new(width as f32, height as f32);
+    let focal = Vec2::new(
+        projection[(0, 0)] * viewport.x * 0.5,
+        projection[(1, 1)] * viewport.y * 0.5,
+    );
+
+    let mut splats: Vec<ReferenceSplat> = gaussians
+        .iter()
+        .filter_map(|gaussian| {
+            let object = objects
+                .get(gaussian.object as usize)
+                .filter(|object| object.visible != 0)?;
+            preprocess(
+                gaussian,
+                &Mat4::from(object.model),
+                view,
+                projection,
+                viewport,
+                focal,
+                filter_mode,
+            )
+        })
+        .collect();
+    splats.sort_by_key(|splat| splat.depth_key);
+
+    let mut image = image::Rgba32FImage::new(width, height);
+    for splat in &splats {
+        rasterize(&mut image, splat, viewport);
+    }
+    image
+}
+
+fn preprocess(
+    gaussian: &GpuGaussian,
+    model: &Mat4,
+    view: &Mat4,
+    projection: &Mat4,
+    viewport: Vec2,
+    focal: Vec2,
+    filter_mode: FilterMode,
+) -> Option<ReferenceSplat> {
+    let position = Vec3::from(gaussian.position);
+    let view_pos = view * model * position.push(1.0);
+
+    if view_pos.z >= -0.1 {
+        return None;
+    }
+
+    let clip_pos = projection * view_pos;
+    let ndc = clip_pos.xyz() / clip_pos.w;
+
+    if ndc.x.abs() > 1.3 || ndc.y.abs() > 1.3 {
+        return None;
+    }
+
+    let center = Vec2::new(
+        (ndc.x * 0.5 + 0.5) * viewport.x,
+        (ndc.y * 0.5 + 0.5) * viewport.y,
+    );
+
+    let [r, x, y, z] = gaussian.rotation;
+    let rotation_matrix = Mat3::from_columns(&[
+        Vec3::new(
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y + r * z),
+            2.0 * (x * z - r * y),
+        ),
+        Vec3::new(
+            2.0 * (x * y - r * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z + r * x),
+        ),
+        Vec3::new(
+            2.0 * (x * z + r * y),
+            2.0 * (y * z - r * x),
+            1.0 - 2.0 * (x * x + y * y),
+        ),
+    ]);
+
+    let mip_splatting = filter_mode == FilterMode::MipSplatting;
+    let mut opacity_scale = 1.0;
+
+    let mut scale = Vec3::from(gaussian.scale_log).map(f32::exp);
+    if mip_splatting {
+        let scale_squared = scale.component_mul(&scale);
+        let filtered_squared = scale_squared.add_scalar(gaussian.filter_variance);
+        opacity_scale = (scale_squared.product() / filtered_squared.product()).sqrt();
+        scale = filtered_squared.map(f32::sqrt);
+    }
+
+    let m = rotation_matrix * Mat3::from_diagonal(&scale);
+    let sigma = m * m.transpose();
+
+    let view3x3: Mat3 = view.fixed_view::<3, 3>(0, 0).into();
+    let model3x3: Mat3 = model.fixed_view::<3, 3>(0, 0).into();
+
+    let tz = view_pos.z;
+    let jacobian = if projection[(3, 3)] == 1.0 {
+        Mat3::from_diagonal(&Vec3::new(focal.x, focal.y, 0.0))
+    } else {
+        Mat3::from_columns(&[
+            Vec3::new(focal.x / tz, 0.0, 0.0),
+            Vec3::new(0.0, focal.y / tz, 0.0),
+            Vec3::new(
+                -focal.x * view_pos.x / (tz * tz),
+                -focal.y * view_pos.y / (tz * tz),
+                0.0,
+            ),
+        ])
+    };
+
+    let t = jacobian * view3x3 * model3x3;
+    let cov2d = t * sigma * t.transpose();
+
+    let dilation = if mip_splatting {
+        MIP_KERNEL_VARIANCE
+    } else {
+        LEGACY_DILATION
+    };
+    let cov_a = cov2d[(0, 0)] + dilation;
+    let cov_b = cov2d[(1, 0)];
+    let cov_d = cov2d[(1, 1)] + dilation;
+
+    let det = cov_a * cov_d - cov_b * cov_b;
+    if det <= 0.0 {
+        return None;
+    }
+
+    if mip_splatting {
+        let unfiltered_det = cov2d[(0, 0)] * cov2d[(1, 1)] - cov_b * cov_b;
+        opacity_scale *= (unfiltered_det / det).max(0.000025).sqrt();
+    }
+
+    let conic = Vec3::new(cov_d / det, -cov_b / det, cov_a / det);
+
+    let opacity = opacity_scale / (1.0 + (-gaussian.opacity_logit).exp());
+
+    if opacity < 1.0 / 255.0 {
+        return None;
+    }
+
+    let extent = (2.0 * (255.0 * opacity).ln()).sqrt();
+
+    let mid = 0.5 * (cov_a + cov_d);
+    let discriminant = (mid * mid - det).max(0.0).sqrt();
+    let lambda_major = mid + discriminant;
+    let lambda_minor = (mid - discriminant).max(0.0);
+    let pixel_radius = (extent * lambda_major.sqrt()).ceil();
+
+    if pixel_radius <= 0.0 || pixel_radius > 1024.0 {
+        return None;
+    }
+
+    let major_direction = if cov_b.abs() > 1e-6 {
+        Vec2::new(cov_b, lambda_major - cov_a).normalize()
+    } else if cov_d > cov_a {
+        Vec2::new(0.0, 1.0)
+    } else {
+        Vec2::new(1.0, 0.0)
+    };
+    let minor_direction = Vec2::new(-major_direction.y, major_direction.x);
+
+    let color =
+        Vec3::from(gaussian.sh_dc).map(|coefficient| (coefficient * SH_C0 + 0.5).clamp(0.0, 1.0));
+
+    Some(ReferenceSplat {
+        color,
+        conic,
+        opacity,
+        center,
+        major_axis: major_direction * extent * lambda_major.sqrt(),
+        minor_axis: minor_direction * extent * lambda_minor.sqrt(),
+        depth_key: u32::MAX - (-view_pos.z).to_bits(),
+    })
+}
+
+fn rasterize(image: &mut image::Rgba32FImage, splat: &ReferenceSplat, viewport: Vec2) {
+    let major_length_squared = splat.major_axis.norm_squared();
+    let minor_length_squared = splat.minor_axis.norm_squared();
+    if major_length_squared <= 0.0 || minor_length_squared <= 0.0 {
+        return;
+    }
+
+    let half_extent = splat.major_axis.abs() + splat.minor_axis.abs();
+    let min_x = (splat.center.x - half_extent.x).floor().max(0.0) as u32;
+    let max_x = (splat.center.x + half_extent.x).ceil().min(viewport.x) as u32;
+    let min_y = (viewport.y - splat.center.y - half_extent.y)
+        .floor()
+        .max(0.0) as u32;
+    let max_y = (viewport.y - splat.center.y + half_extent.y)
+        .ceil()
+        .min(viewport.y) as u32;
+
+    for pixel_y in min_y..max_y {
+        for pixel_x in min_x..max_x {
+            let screen = Vec2::new(pixel_x as f32 + 0.5, viewport.y - (pixel_y as f32 + 0.5));
+            let offset = screen - splat.center;
+
+            let along_major = offset.dot(&splat.major_axis) / major_length_squared;
+            let along_minor = offset.dot(&splat.minor_axis) / minor_length_squared;
+            if along_major.abs() > 1.0 || along_minor.abs() > 1.0 {
+                continue;
+            }
+
+            let power = -0.5
+                * (splat.conic.x * offset.x * offset.x
+                    + 2.0 * splat.conic.y * offset.x * offset.y
+                    + splat.conic.z * offset.y * offset.y);
+            if power > 0.0 {
+                continue;
+            }
+
+            let alpha = (splat.opacity * power.exp()).min(0.99);
+            if alpha < 1.0 / 255.0 {
+                continue;
+            }
+
+            let destination = image.get_pixel_mut(pixel_x, pixel_y);
+            let transmittance = 1.0 - alpha;
+            for channel in 0..3 {
+                destination[channel] =
+                    splat.color[channel] * alpha + destination[channel] * transmittance;
+            }
+            destination[3] = alpha + destination[3] * transmittance;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera_path::CameraPose;
+    use crate::headless::HeadlessRenderer;
+    use crate::splat_pass::SplatSettings;
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 64;
+    const TOLERANCE: u8 = 2;
+    const GPU_TOLERANCE: f32 = 0.02;
+
+    fn gaussian(position: [f32; 3], color: [f32; 3], scale: [f32; 3], angle: f32) -> GpuGaussian {
+        let rotation = nalgebra_glm::quat_angle_axis(angle, &Vec3::z());
+        GpuGaussian {
+            position,
+            opacity_logit: 2.0,
+            sh_dc: color.map(|channel| (channel - 0.5) / SH_C0),
+            object: 0,
+            scale_log: scale.map(f32::ln),
+            filter_variance: 0.0,
+            rotation: [rotation.w, rotation.i, rotation.j, rotation.k],
+        }
+    }
+
+    fn overlapping_gaussians() -> Vec<GpuGaussian> {
+        vec![
+            gaussian([-0.3, 0.1, -0.5], [1.0, 0.1, 0.1], [0.6, 0.15, 0.15], 0.4),
+            gaussian([0.0, -0.1, 0.0], [0.1, 1.0, 0.1], [0.15, 0.6, 0.15], -0.3),
+            gaussian([0.3, 0.2, 0.5], [0.1, 0.1, 1.0], [0.4, 0.25, 0.2], 1.2),
+        ]
+    }
+
+    fn perspective() -> Mat4 {
+        PerspectiveCamera::default().matrix_with_aspect(WIDTH as f32 / HEIGHT as f32)
+    }
+
+    fn view() -> Mat4 {
+        nalgebra_glm::look_at(&Vec3::new(0.0, 0.0, 4.0), &Vec3::zeros(), &Vec3::y())
+    }
+
+    fn assert_matches_reference(name: &str, image: &image::Rgba32FImage) {
+        let quantize = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let actual = image::RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+            image::Rgba(image.get_pixel(x, y).0.map(quantize))
+        });
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/reference")
+            .join(format!("{name}.png"));
+        if std::env::var_os("UPDATE_REFERENCE_IMAGES").is_some() {
+            actual.save(&path).expect("failed to write reference image");
+            return;
+        }
+        let expected = image::open(&path)
+            .unwrap_or_else(|error| panic!("failed to open {}: {error}", path.display()))
+            .to_rgba8();
+        assert_eq!(expected.dimensions(), actual.dimensions());
+        let difference = expected
+            .pixels()
+            .zip(actual.pixels())
+            .flat_map(|(expected, actual)| {
+                expected
+                    .0
+                    .into_iter()
+                    .zip(actual.0)
+                    .map(|(expected, actual)| expected.abs_diff(actual))
+            })
+            .max()
+            .unwrap_or(0);
+        assert!(
+            difference <= TOLERANCE,
+            "{name} differs from its reference image by {difference}"
+        );
+    }
+
+    #[test]
+    fn single_gaussian() {
+        let gaussians = [gaussian([0.0; 3], [1.0, 0.6, 0.2], [0.3; 3], 0.0)];
+        let image = render_reference(
+            &gaussians,
+            &[GpuSplatObject::new(Mat4::identity(), true)],
+            &view(),
+            &perspective(),
+            WIDTH,
+            HEIGHT,
+            FilterMode::Legacy,
+        );
+        assert_matches_reference("single_gaussian", &image);
+    }
+
+    #[test]
+    fn overlapping_gaussians_sorted_by_depth() {
+        let image = render_reference(
+            &overlapping_gaussians(),
+            &[GpuSplatObject::new(Mat4::identity(), true)],
+            &view(),
+            &perspective(),
+            WIDTH,
+            HEIGHT,
+            FilterMode::Legacy,
+        );
+        assert_matches_reference("overlapping_gaussians", &image);
+    }
+
+    #[test]
+    fn orthographic_projection() {
+        let projection = OrthographicCamera {
+            x_mag: 1.5,
+            y_mag: 1.5,
+            z_near: 0.1,
+            z_far: 10.0,
+        }
+        .matrix();
+        let image = render_reference(
+            &overlapping_gaussians(),
+            &[GpuSplatObject::new(Mat4::identity(), true)],
+            &view(),
+            &projection,
+            WIDTH,
+            HEIGHT,
+            FilterMode::Legacy,
+        );
+        assert_matches_reference("orthographic", &image);
+    }
+
+    #[test]
+    fn mip_splatting_filter() {
+        let gaussians: Vec<GpuGaussian> = (0..25)
+            .map(|index| GpuGaussian {
+                filter_variance: 0.0025,
+                ..gaussian(
+                    [
+                        (index % 5) as f32 * 0.4 - 0.8,
+                        (index / 5) as f32 * 0.4 - 0.8,
+                        0.0,
+                    ],
+                    [0.9, 0.9, 0.3],
+                    [0.03, 0.03, 0.03],
+                    0.0,
+                )
+            })
+            .collect();
+        let image = render_reference(
+            &gaussians,
+            &[GpuSplatObject::new(Mat4::identity(), true)],
+            &view(),
+            &perspective(),
+            WIDTH,
+            HEIGHT,
+            FilterMode::MipSplatting,
+        );
+        assert_matches_reference("mip_splatting", &image);
+    }
+
+    #[test]
+    fn objects_place_and_hide_their_gaussians() {
+        let placed = [gaussian([0.5, 0.0, 0.0], [1.0, 0.6, 0.2], [0.3; 3], 0.0)];
+        let expected = render_reference(
+            &placed,
+            &[GpuSplatObject::new(Mat4::identity(), true)],
+            &view(),
+            &perspective(),
+            WIDTH,
+            HEIGHT,
+            FilterMode::Legacy,
+        );
+        let gaussians = [
+            GpuGaussian {
+                object: 1,
+                ..gaussian([0.0; 3], [1.0, 0.6, 0.2], [0.3; 3], 0.0)
+            },
+            gaussian([0.0; 3], [0.1, 0.1, 1.0], [0.3; 3], 0.0),
+        ];
+        let objects = [
+            GpuSplatObject::new(Mat4::identity(), false),
+            GpuSplatObject::new(nalgebra_glm::translation(&Vec3::new(0.5, 0.0, 0.0)), true),
+        ];
+        let image = render_reference(
+            &gaussians,
+            &objects,
+            &view(),
+            &perspective(),
+            WIDTH,
+            HEIGHT,
+            FilterMode::Legacy,
+        );
+        assert!(image == expected);
+    }
+
+    #[test]
+    fn gpu_matches_cpu_reference() {
+        let gaussians = overlapping_gaussians();
+        for filter_mode in [FilterMode::Legacy, FilterMode::MipSplatting] {
+            let settings = SplatSettings {
+                filter_mode,
+                ..SplatSettings::default()
+            };
+            let mut renderer = match HeadlessRenderer::new(&gaussians, WIDTH, HEIGHT, settings) {
+                Ok(renderer) => renderer,
+                Err(error) => {
+                    eprintln!("Skipping GPU comparison, no headless device: {error}");
+                    return;
+                }
+            };
+            let objects = vec![GpuSplatObject::new(Mat4::identity(), true)];
+            renderer.set_objects(objects.clone());
+            let pose = CameraPose {
+                position: Vec3::new(0.0, 0.0, 4.0),
+                target: Vec3::zeros(),
+                y_fov_rad: PerspectiveCamera::default().y_fov_rad,
+            };
+            let gpu = renderer.render(&pose).expect("headless render failed");
+            let (view, projection) = pose.matrices(WIDTH, HEIGHT);
+            let cpu = render_reference(
+                &gaussians,
+                &objects,
+                &view,
+                &projection,
+                WIDTH,
+                HEIGHT,
+                filter_mode,
+            );
+            let difference = gpu
+                .pixels()
+                .zip(cpu.pixels())
+                .flat_map(|(gpu, cpu)| gpu.0.into_iter().zip(cpu.0))
+                .map(|(gpu, cpu)| (gpu.clamp(0.0, 1.0) - cpu.clamp(0.0, 1.0)).abs())
+                .fold(0.0, f32::max);
+            assert!(
+                difference <= GPU_TOLERANCE,
+                "GPU render differs from the CPU reference by {difference}"
+            );
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::bounds::SceneBounds;
+use crate::cameras::TrainingCamera;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gaussian::RawGaussian;
+use crate::gaussian::{GpuGaussian, SplatTransform};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sh_rotation::ShRotation;
+use nightshade::prelude::nalgebra_glm::Mat3;
+use nightshade::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const GROUND_FIT_SAMPLES: usize = 200_000;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneTransform {
+    pub rotation: [f32; 4],
+    pub translation: [f32; 3],
+    pub scale: f32,
+}
+
+impl Default for SceneTransform {
+    fn default() -> Self {
+        Self {
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            translation: [0.0; 3],
+            scale: 1.0,
+        }
+    }
+}
+
+impl SceneTransform {
+    pub fn rotation_quat(&self) -> Quat {
+        nalgebra_glm::quat_normalize(&Quat::from(Vec4::from(self.rotation)))
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        nalgebra_glm::translation(&Vec3::from(self.translation))
+            * nalgebra_glm::quat_to_mat4(&self.rotation_quat())
+            * nalgebra_glm::scaling(&Vec3::repeat(self.scale))
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.rotation_quat(), &(point * self.scale))
+            + Vec3::from(self.translation)
+    }
+
+    pub fn inverse_transform_point(&self, point: Vec3) -> Vec3 {
+        self.inverse_transform_vector(point - Vec3::from(self.translation))
+    }
+
+    pub fn inverse_transform_vector(&self, vector: Vec3) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(
+            &nalgebra_glm::quat_conjugate(&self.rotation_quat()),
+            &vector,
+        ) / self.scale
+    }
+
+    pub fn model_transform(&self, transform: &SplatTransform) -> SplatTransform {
+        let rotation = self.rotation_quat();
+        let translation = Vec3::from(self.translation);
+        SplatTransform {
+            rotation: nalgebra_glm::quat_conjugate(&rotation) * transform.rotation * rotation,
+            scale: transform.scale,
+            translation: self.inverse_transform_vector(
+                nalgebra_glm::quat_rotate_vec3(&transform.rotation, &translation) * transform.scale
+                    + transform.translation
+                    - translation,
+            ),
+        }
+    }
+
+    pub fn set_up(&mut self, up: Vec3) {
+        let up = up.normalize();
+        let rotation = if up.y < -0.9999 {
+            nalgebra_glm::quat_angle_axis(std::f32::consts::PI, &Vec3::x())
+        } else {
+            nalgebra_glm::quat_rotation(&up, &Vec3::y())
+        };
+        self.rotation = rotation.coords.into();
+    }
+
+    pub fn rotate(&mut self, axis: Vec3, degrees: f32) {
+        let rotation =
+            nalgebra_glm::quat_angle_axis(degrees.to_radians(), &axis) * self.rotation_quat();
+        self.rotation = nalgebra_glm::quat_normalize(&rotation).coords.into();
+    }
+}
+
+pub fn estimate_up(cameras: &[TrainingCamera], gaussians: &[GpuGaussian]) -> Option<Vec3> {
+    camera_up(cameras).or_else(|| ground_plane_up(gaussians))
+}
+
+fn camera_up(cameras: &[TrainingCamera]) -> Option<Vec3> {
+    let sum: Vec3 = cameras
+        .iter()
+        .map(|camera| {
+            -Vec3::new(
+                camera.rotation[0][1],
+                camera.rotation[1][1],
+                camera.rotation[2][1],
+            )
+        })
+        .sum();
+    (sum.norm() > 1e-3 * cameras.len() as f32).then(|| sum.normalize())
+}
+
+fn ground_plane_up(gaussians: &[GpuGaussian]) -> Option<Vec3> {
+    let bounds = SceneBounds::from_gaussians(gaussians)?;
+    let stride = gaussians.len().div_ceil(GROUND_FIT_SAMPLES).max(1);
+    let points: Vec<Vec3> = gaussians
+        .iter()
+        .step_by(stride)
+        .map(|gaussian| Vec3::from(gaussian.position))
+        .filter(|point| {
+            point.iter().all(|value| value.is_finite())
+                && (0..3).all(|axis| (bounds.min[axis]..=bounds.max[axis]).contains(&point[axis]))
+        })
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+
+    let count = points.len() as f32;
+    let mean = points.iter().sum::<Vec3>() / count;
+    let covariance = points.iter().fold(Mat3::zeros(), |sum, point| {
+        let offset = point - mean;
+        sum + offset * offset.transpose()
+    }) / count;
+
+    let eigen = covariance.symmetric_eigen();
+    let smallest = eigen.eigenvalues.imin();
+    let normal: Vec3 = eigen.eigenvectors.column(smallest).into();
+
+    let skew: f32 = points
+        .iter()
+        .map(|point| (point - mean).dot(&normal).powi(3))
+        .sum();
+    Some(if skew < 0.0 { -normal } else { normal })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn bake_scene_transform(transform: &SceneTransform, gaussians: &mut [RawGaussian]) {
+    let rotation = transform.rotation_quat();
+    let sh_rotation = ShRotation::new(&rotation);
+    let log_scale = transform.scale.ln();
+    for gaussian in gaussians {
+        gaussian.position = transform
+            .transform_point(Vec3::from(gaussian.position))
+            .into();
+        let [w, x, y, z] = gaussian.rotation;
+        let rotated = rotation * Quat::new(w, x, y, z);
+        gaussian.rotation = [rotated.w, rotated.i, rotated.j, rotated.k];
+        gaussian.scale = Vec3::from(gaussian.scale).add_scalar(log_scale).into();
+        gaussian.rotate_sh(&sh_rotation);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn transform_path(ply_path: &std::path::Path) -> std::path::PathBuf {
+    ply_path.with_extension("transform.json")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_scene_transform(path: &std::path::Path) -> Option<SceneTransform> {
+    let bytes = std::fs::read(path).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(transform) => Some(transform),
+        Err(error) => {
+            eprintln!("Failed to parse {}: {error}", path.display());
+            None
+        }
+    }
+}
+
+pub fn save_scene_transform(
+    path: &std::path::Path,
+    transform: &SceneTransform,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, serde_json::to_vec_pretty(transform)?)?;
+    Ok(())
+}
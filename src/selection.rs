@@ -0,0 +1,301 @@
+use crate::crop::{self, CropVolume};
+use crate::edits::Edits;
+use crate::gaussian::GpuGaussian;
+use nightshade::prelude::*;
+
+const CLICK_RADIUS_PIXELS: f32 = 8.0;
+const MIN_VISIBLE_OPACITY: f32 = 1.0 / 255.0;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SelectionMode {
+    Click,
+    Rectangle,
+    Lasso,
+    Brush,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SelectionOperation {
+    Replace,
+    Add,
+    Subtract,
+}
+
+pub struct SelectionDrag {
+    pub operation: SelectionOperation,
+    pub points: Vec<Vec2>,
+}
+
+pub struct Selection {
+    words: Vec<u32>,
+    len: usize,
+    count: usize,
+    dirty: bool,
+}
+
+impl Selection {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(32).max(1)],
+            len,
+            count: 0,
+            dirty: false,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 32] & (1 << (index % 32)) != 0
+    }
+
+    pub fn set(&mut self, index: usize, selected: bool) {
+        if index >= self.len || self.contains(index) == selected {
+            return;
+        }
+        self.words[index / 32] ^= 1 << (index % 32);
+        if selected {
+            self.count += 1;
+        } else {
+            self.count -= 1;
+        }
+        self.dirty = true;
+    }
+
+    pub fn clear(&mut self) {
+        if self.count > 0 {
+            self.words.fill(0);
+            self.count = 0;
+            self.dirty = true;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| **word != 0)
+            .flat_map(|(word_index, word)| {
+                (0..32)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| word_index * 32 + bit)
+            })
+    }
+
+    pub fn invert(&mut self, selectable: impl Fn(usize) -> bool) {
+        for index in 0..self.len {
+            let selected = !self.contains(index) && selectable(index);
+            self.set(index, selected);
+        }
+    }
+
+    pub fn apply(&mut self, operation: SelectionOperation, indices: impl Iterator<Item = usize>) {
+        if operation == SelectionOperation::Replace {
+            self.clear();
+        }
+        let selected = operation != SelectionOperation::Subtract;
+        for index in indices {
+            self.set(index, selected);
+        }
+    }
+
+    pub fn take_dirty(&mut self) -> Option<&[u32]> {
+        std::mem::take(&mut self.dirty).then_some(self.words.as_slice())
+    }
+}
+
+pub struct ScreenProjection {
+    model_view: Mat4,
+    model_view_projection: Mat4,
+    viewport: Vec2,
+}
+
+impl ScreenProjection {
+    pub fn new(model: &Mat4, view: &Mat4, projection: &Mat4, viewport: Vec2) -> Self {
+        Self {
+            model_view: view * model,
+            model_view_projection: projection * view * model,
+            viewport,
+        }
+    }
+
+    pub fn project(&self, position: Vec3) -> Option<(Vec2, f32)> {
+        let view_pos = self.model_view * position.push(1.0);
+        if view_pos.z >= -0.1 {
+            return None;
+        }
+        let clip_pos = self.model_view_projection * position.push(1.0);
+        let ndc = clip_pos.xyz() / clip_pos.w;
+        if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+            return None;
+        }
+        Some((
+            Vec2::new(
+                (ndc.x * 0.5 + 0.5) * self.viewport.x,
+                (0.5 - ndc.y * 0.5) * self.viewport.y,
+            ),
+            -view_pos.z,
+        ))
+    }
+}
+
+pub fn visible_splats<'a>(
+    gaussians: &'a [GpuGaussian],
+    projection: &'a ScreenProjection,
+    crop_volumes: &'a [CropVolume],
+    edits: &'a Edits,
+) -> impl Iterator<Item = (usize, Vec2, f32)> + 'a {
+    gaussians
+        .iter()
+        .enumerate()
+        .filter(|(index, gaussian)| {
+            edits.is_active(*index)
+                && 1.0 / (1.0 + (-gaussian.opacity_logit).exp()) >= MIN_VISIBLE_OPACITY
+                && crop::keeps(crop_volumes, Vec3::from(gaussian.position))
+        })
+        .filter_map(|(index, gaussian)| {
+            let (screen, depth) = projection.project(Vec3::from(gaussian.position))?;
+            Some((index, screen, depth))
+        })
+}
+
+pub fn pick(splats: impl Iterator<Item = (usize, Vec2, f32)>, cursor: Vec2) -> Option<usize> {
+    splats
+        .filter(|(_, screen, _)| (screen - cursor).norm() <= CLICK_RADIUS_PIXELS)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(index, _, _)| index)
+}
+
+pub fn in_rectangle(
+    splats: impl Iterator<Item = (usize, Vec2, f32)>,
+    corner: Vec2,
+    opposite: Vec2,
+) -> impl Iterator<Item = usize> {
+    let min = corner.inf(&opposite);
+    let max = corner.sup(&opposite);
+    splats
+        .filter(move |(_, screen, _)| {
+            screen.x >= min.x && screen.x <= max.x && screen.y >= min.y && screen.y <= max.y
+        })
+        .map(|(index, _, _)| index)
+}
+
+pub fn in_lasso<'a>(
+    splats: impl Iterator<Item = (usize, Vec2, f32)> + 'a,
+    polygon: &'a [Vec2],
+) -> impl Iterator<Item = usize> + 'a {
+    splats
+        .filter(move |(_, screen, _)| polygon.len() >= 3 && inside_polygon(polygon, *screen))
+        .map(|(index, _, _)| index)
+}
+
+pub fn in_sphere<'a>(
+    splats: impl Iterator<Item = (usize, Vec2, f32)> + 'a,
+    gaussians: &'a [GpuGaussian],
+    center: Vec3,
+    radius: f32,
+) -> impl Iterator<Item = usize> + 'a {
+    splats.map(|(index, _, _)| index).filter(move |index| {
+        (Vec3::from(gaussians[*index].position) - center).norm_squared() <= radius * radius
+    })
+}
+
+fn inside_polygon(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for current in polygon {
+        if (current.y > point.y) != (previous.y > point.y)
+            && point.x
+                < (previous.x - current.x) * (point.y - current.y) / (previous.y - current.y)
+                    + current.x
+        {
+            inside = !inside;
+        }
+        previous = *current;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn splats(points: &[[f32; 2]]) -> impl Iterator<Item = (usize, Vec2, f32)> + '_ {
+        points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| (index, Vec2::new(point[0], point[1]), 1.0))
+    }
+
+    fn u_shape() -> Vec<Vec2> {
+        [
+            [0.0, 0.0],
+            [30.0, 0.0],
+            [30.0, 30.0],
+            [20.0, 30.0],
+            [20.0, 10.0],
+            [10.0, 10.0],
+            [10.0, 30.0],
+            [0.0, 30.0],
+        ]
+        .map(|[x, y]| Vec2::new(x, y))
+        .to_vec()
+    }
+
+    #[test]
+    fn concave_lasso_excludes_its_notch() {
+        let polygon = u_shape();
+        let points = [
+            [5.0, 25.0],
+            [25.0, 25.0],
+            [15.0, 5.0],
+            [15.0, 20.0],
+            [15.0, 29.0],
+            [35.0, 5.0],
+            [-1.0, 15.0],
+        ];
+        let selected: Vec<usize> = in_lasso(splats(&points), &polygon).collect();
+        assert_eq!(selected, [0, 1, 2]);
+
+        let reversed: Vec<Vec2> = polygon.iter().rev().copied().collect();
+        let selected: Vec<usize> = in_lasso(splats(&points), &reversed).collect();
+        assert_eq!(selected, [0, 1, 2]);
+
+        assert_eq!(in_lasso(splats(&points), &polygon[..2]).count(), 0);
+    }
+
+    #[test]
+    fn rectangle_accepts_corners_in_any_order() {
+        let points = [[5.0, 5.0], [15.0, 5.0], [5.0, 15.0], [10.0, 10.0]];
+        let forward: Vec<usize> =
+            in_rectangle(splats(&points), Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)).collect();
+        let backward: Vec<usize> =
+            in_rectangle(splats(&points), Vec2::new(10.0, 10.0), Vec2::new(0.0, 0.0)).collect();
+        assert_eq!(forward, [0, 3]);
+        assert_eq!(backward, forward);
+    }
+
+    #[test]
+    fn operations_replace_add_and_subtract() {
+        let mut selection = Selection::new(70);
+        selection.apply(SelectionOperation::Replace, [1, 40, 69].into_iter());
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [1, 40, 69]);
+        assert!(selection.take_dirty().is_some());
+        assert!(selection.take_dirty().is_none());
+
+        selection.apply(SelectionOperation::Add, [2, 40].into_iter());
+        assert_eq!(selection.count(), 4);
+        selection.apply(SelectionOperation::Subtract, [1, 3].into_iter());
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [2, 40, 69]);
+        selection.apply(SelectionOperation::Replace, [5, 70].into_iter());
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [5]);
+
+        selection.invert(|index| index % 2 == 1);
+        assert_eq!(selection.count(), 34);
+        assert!(!selection.contains(5));
+        assert!(selection.contains(7));
+        assert!(!selection.contains(8));
+    }
+}
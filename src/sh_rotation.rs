@@ -0,0 +1,193 @@
+use nightshade::prelude::nalgebra_glm::{TMat, TVec};
+use nightshade::prelude::*;
+
+pub const SH_COEFFICIENTS_PER_CHANNEL: usize = 15;
+
+const SH_C1: f32 = 0.488_602_52;
+const SH_C2: [f32; 5] = [
+    1.092_548_4,
+    -1.092_548_4,
+    0.315_391_57,
+    -1.092_548_4,
+    0.546_274_2,
+];
+const SH_C3: [f32; 7] = [
+    -0.590_043_6,
+    2.890_611_4,
+    -0.457_045_8,
+    0.373_176_33,
+    -0.457_045_8,
+    1.445_305_7,
+    -0.590_043_6,
+];
+const SAMPLE_DIRECTIONS: [[f32; 3]; 7] = [
+    [0.31, 0.82, -0.48],
+    [-0.67, 0.25, 0.70],
+    [0.54, -0.61, 0.58],
+    [0.93, 0.12, 0.35],
+    [-0.21, -0.88, -0.43],
+    [-0.45, 0.57, -0.69],
+    [0.08, -0.19, 0.98],
+];
+
+pub struct ShRotation {
+    band1: TMat<f32, 3, 3>,
+    band2: TMat<f32, 5, 5>,
+    band3: TMat<f32, 7, 7>,
+}
+
+impl ShRotation {
+    pub fn new(rotation: &Quat) -> Self {
+        let inverse = nalgebra_glm::quat_conjugate(&nalgebra_glm::quat_normalize(rotation));
+        Self {
+            band1: band_rotation(&inverse, 0),
+            band2: band_rotation(&inverse, 3),
+            band3: band_rotation(&inverse, 8),
+        }
+    }
+
+    pub fn apply(&self, coefficients: &mut [f32]) {
+        rotate_band(&self.band1, &mut coefficients[0..3]);
+        rotate_band(&self.band2, &mut coefficients[3..8]);
+        rotate_band(&self.band3, &mut coefficients[8..15]);
+    }
+}
+
+pub fn sh_basis(direction: &Vec3) -> [f32; SH_COEFFICIENTS_PER_CHANNEL] {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (xx, yy, zz) = (x * x, y * y, z * z);
+    [
+        -SH_C1 * y,
+        SH_C1 * z,
+        -SH_C1 * x,
+        SH_C2[0] * x * y,
+        SH_C2[1] * y * z,
+        SH_C2[2] * (2.0 * zz - xx - yy),
+        SH_C2[3] * x * z,
+        SH_C2[4] * (xx - yy),
+        SH_C3[0] * y * (3.0 * xx - yy),
+        SH_C3[1] * x * y * z,
+        SH_C3[2] * y * (4.0 * zz - xx - yy),
+        SH_C3[3] * z * (2.0 * zz - 3.0 * xx - 3.0 * yy),
+        SH_C3[4] * x * (4.0 * zz - xx - yy),
+        SH_C3[5] * z * (xx - yy),
+        SH_C3[6] * x * (xx - 3.0 * yy),
+    ]
+}
+
+fn band_rotation<const N: usize>(inverse: &Quat, offset: usize) -> TMat<f32, N, N> {
+    let directions = SAMPLE_DIRECTIONS.map(|direction| Vec3::from(direction).normalize());
+    let samples =
+        TMat::<f32, N, N>::from_fn(|row, column| sh_basis(&directions[row])[offset + column]);
+    let rotated = TMat::<f32, N, N>::from_fn(|row, column| {
+        sh_basis(&nalgebra_glm::quat_rotate_vec3(inverse, &directions[row]))[offset + column]
+    });
+    samples
+        .try_inverse()
+        .expect("SH sample directions must span every band")
+        * rotated
+}
+
+fn rotate_band<const N: usize>(matrix: &TMat<f32, N, N>, coefficients: &mut [f32]) {
+    let rotated = matrix * TVec::<f32, N>::from_column_slice(coefficients);
+    coefficients.copy_from_slice(rotated.as_slice());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn test_directions() -> Vec<Vec3> {
+        (0..32)
+            .map(|index| {
+                let z = 1.0 - (index as f32 + 0.5) / 16.0;
+                let radius = (1.0 - z * z).sqrt();
+                let angle = index as f32 * 2.399_963;
+                Vec3::new(radius * angle.cos(), radius * angle.sin(), z)
+            })
+            .collect()
+    }
+
+    fn test_rotations() -> Vec<Quat> {
+        vec![
+            nalgebra_glm::quat_angle_axis(0.7, &Vec3::new(0.0, 0.0, 1.0)),
+            nalgebra_glm::quat_angle_axis(1.9, &Vec3::new(1.0, 0.0, 0.0)),
+            nalgebra_glm::quat_angle_axis(-2.4, &Vec3::new(1.0, -2.0, 0.5).normalize()),
+            nalgebra_glm::quat_angle_axis(
+                std::f32::consts::PI,
+                &Vec3::new(0.3, 0.4, -0.8).normalize(),
+            ),
+        ]
+    }
+
+    fn band_coefficients(band: std::ops::Range<usize>) -> [f32; SH_COEFFICIENTS_PER_CHANNEL] {
+        let mut coefficients = [0.0; SH_COEFFICIENTS_PER_CHANNEL];
+        for index in band {
+            coefficients[index] = ((index * 7 + 3) % 11) as f32 / 5.0 - 1.0;
+        }
+        coefficients
+    }
+
+    fn evaluate(coefficients: &[f32], direction: &Vec3) -> f32 {
+        sh_basis(direction)
+            .iter()
+            .zip(coefficients)
+            .map(|(basis, coefficient)| basis * coefficient)
+            .sum()
+    }
+
+    #[test]
+    fn rotated_coefficients_match_rotated_directions() {
+        for band in [0..3, 3..8, 8..15] {
+            let coefficients = band_coefficients(band.clone());
+            for rotation in test_rotations() {
+                let mut rotated = coefficients;
+                ShRotation::new(&rotation).apply(&mut rotated);
+                for direction in test_directions() {
+                    let expected = evaluate(&coefficients, &direction);
+                    let actual = evaluate(
+                        &rotated,
+                        &nalgebra_glm::quat_rotate_vec3(&rotation, &direction),
+                    );
+                    assert!(
+                        (expected - actual).abs() < TOLERANCE,
+                        "band {band:?}: expected {expected}, got {actual}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn identity_rotation_keeps_coefficients() {
+        let coefficients = band_coefficients(0..SH_COEFFICIENTS_PER_CHANNEL);
+        let mut rotated = coefficients;
+        ShRotation::new(&Quat::identity()).apply(&mut rotated);
+        for (expected, actual) in coefficients.iter().zip(&rotated) {
+            assert!((expected - actual).abs() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn composed_rotation_matches_sequential_rotations() {
+        let rotations = test_rotations();
+        let coefficients = band_coefficients(0..SH_COEFFICIENTS_PER_CHANNEL);
+        for first in &rotations {
+            for second in &rotations {
+                let mut composed = coefficients;
+                ShRotation::new(&(first * second)).apply(&mut composed);
+                let mut sequential = coefficients;
+                ShRotation::new(second).apply(&mut sequential);
+                ShRotation::new(first).apply(&mut sequential);
+                for (expected, actual) in sequential.iter().zip(&composed) {
+                    assert!(
+                        (expected - actual).abs() < TOLERANCE,
+                        "expected {expected}, got {actual}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+struct Uniforms {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    viewport: vec2<f32>,
+    focal: vec2<f32>,
+    gaussian_count: u32,
+    padded_count: u32,
+    depth_alpha_threshold: f32,
+    filter_mode: u32,
+    render_mode: u32,
+    heatmap_max: f32,
+    orthographic: u32,
+    crop_count: u32,
+};
+
+struct FragmentCounts {
+    width: u32,
+    height: u32,
+    _pad0: u32,
+    _pad1: u32,
+    counts: array<u32>,
+};
+
+struct OverdrawStats {
+    total_fragments: atomic<u32>,
+    saturated_fragments: atomic<u32>,
+    max_fragments: atomic<u32>,
+    covered_pixels: atomic<u32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+const RENDER_MODE_SATURATED_FRAGMENTS: u32 = 2u;
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read> fragment_counts: FragmentCounts;
+@group(0) @binding(2) var<storage, read_write> stats: OverdrawStats;
+@group(0) @binding(3) var visibility_texture: texture_2d<f32>;
+
+@vertex
+fn vertex_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var output: VertexOutput;
+    output.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return output;
+}
+
+fn turbo(t: f32) -> vec3<f32> {
+    let red_4 = vec4<f32>(0.13572138, 4.61539260, -42.66032258, 132.13108234);
+    let green_4 = vec4<f32>(0.09140261, 2.19418839, 4.84296658, -14.18503333);
+    let blue_4 = vec4<f32>(0.10667330, 12.64194608, -60.58204836, 110.36276771);
+    let red_2 = vec2<f32>(-152.94239396, 59.28637943);
+    let green_2 = vec2<f32>(4.27729857, 2.82956604);
+    let blue_2 = vec2<f32>(-89.90310912, 27.34824973);
+
+    let x = clamp(t, 0.0, 1.0);
+    let v4 = vec4<f32>(1.0, x, x * x, x * x * x);
+    let v2 = v4.zw * v4.z;
+    return vec3<f32>(
+        dot(v4, red_4) + dot(v2, red_2),
+        dot(v4, green_4) + dot(v2, green_2),
+        dot(v4, blue_4) + dot(v2, blue_2),
+    );
+}
+
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<u32>(input.position.xy);
+    if pixel.x >= fragment_counts.width || pixel.y >= fragment_counts.height {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let count = fragment_counts.counts[pixel.y * fragment_counts.width + pixel.x];
+    if count == 0u {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let visible = textureLoad(visibility_texture, pixel, 0).r;
+    let saturated = max(f32(count) - visible, 0.0);
+
+    atomicAdd(&stats.total_fragments, count);
+    atomicAdd(&stats.saturated_fragments, u32(round(saturated)));
+    atomicMax(&stats.max_fragments, count);
+    atomicAdd(&stats.covered_pixels, 1u);
+
+    let value = select(f32(count), saturated, uniforms.render_mode == RENDER_MODE_SATURATED_FRAGMENTS);
+    let t = log2(1.0 + value) / log2(1.0 + max(uniforms.heatmap_max, 1.0));
+    return vec4<f32>(turbo(t), 1.0);
+}
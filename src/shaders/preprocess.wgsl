@@ -2,18 +2,20 @@ struct GpuGaussian {
     position: vec3<f32>,
     opacity_logit: f32,
     sh_dc: vec3<f32>,
-    _pad0: f32,
+    object: u32,
     scale_log: vec3<f32>,
-    _pad1: f32,
+    filter_variance: f32,
     rotation: vec4<f32>,
 };
 
 struct Splat2D {
-    color: vec4<f32>,
+    color: vec3<f32>,
+    index: u32,
     conic_and_opacity: vec4<f32>,
     center: vec2<f32>,
     radius: f32,
-    _pad: f32,
+    depth: f32,
+    axes: vec4<f32>,
 };
 
 struct Uniforms {
@@ -23,10 +25,39 @@ struct Uniforms {
     focal: vec2<f32>,
     gaussian_count: u32,
     padded_count: u32,
-    _pad1: u32,
-    _pad2: u32,
+    depth_alpha_threshold: f32,
+    filter_mode: u32,
+    render_mode: u32,
+    heatmap_max: f32,
+    orthographic: u32,
+    crop_count: u32,
 };
 
+struct SplatObject {
+    model: mat4x4<f32>,
+    visible: u32,
+};
+
+struct CropVolume {
+    center: vec3<f32>,
+    shape: u32,
+    half_extents: vec3<f32>,
+    mode: u32,
+    rotation: vec4<f32>,
+};
+
+const CROP_SHAPE_SPHERE: u32 = 2u;
+const CROP_MODE_REMOVE: u32 = 1u;
+
+const LOD_REPRESENTATIVE: u32 = 0x80000000u;
+const LOD_STATE_CUT: u32 = 1u;
+const LOD_STATE_OPEN: u32 = 2u;
+const LOD_STATES_PER_WORD: u32 = 16u;
+
+const FILTER_MODE_MIP_SPLATTING: u32 = 1u;
+const LEGACY_DILATION: f32 = 0.3;
+const MIP_KERNEL_VARIANCE: f32 = 0.1;
+
 struct DrawIndirect {
     vertex_count: u32,
     instance_count: atomic<u32>,
@@ -40,6 +71,55 @@ struct DrawIndirect {
 @group(0) @binding(3) var<storage, read_write> sort_keys: array<u32>;
 @group(0) @binding(4) var<storage, read_write> sort_values: array<u32>;
 @group(0) @binding(5) var<storage, read_write> draw_indirect: DrawIndirect;
+@group(0) @binding(6) var<storage, read> crop_volumes: array<CropVolume>;
+@group(0) @binding(7) var<storage, read> visibility: array<u32>;
+@group(0) @binding(8) var<storage, read> objects: array<SplatObject>;
+
+fn inside_crop_volume(volume: CropVolume, position: vec3<f32>) -> bool {
+    let offset = position - volume.center;
+    let axis = -volume.rotation.xyz;
+    let local = offset + 2.0 * cross(axis, cross(axis, offset) + volume.rotation.w * offset);
+    if volume.shape == CROP_SHAPE_SPHERE {
+        return dot(local, local) <= volume.half_extents.x * volume.half_extents.x;
+    }
+    return all(abs(local) <= volume.half_extents);
+}
+
+fn passes_crop(position: vec3<f32>) -> bool {
+    var has_keep = false;
+    var inside_keep = false;
+    for (var volume_index = 0u; volume_index < uniforms.crop_count; volume_index++) {
+        let volume = crop_volumes[volume_index];
+        let inside = inside_crop_volume(volume, position);
+        if volume.mode == CROP_MODE_REMOVE {
+            if inside {
+                return false;
+            }
+        } else {
+            has_keep = true;
+            inside_keep = inside_keep || inside;
+        }
+    }
+    return !has_keep || inside_keep;
+}
+
+fn inactive_words() -> u32 {
+    return (uniforms.gaussian_count + 31u) / 32u;
+}
+
+fn is_inactive(index: u32) -> bool {
+    return (visibility[index / 32u] & (1u << (index % 32u))) != 0u;
+}
+
+fn lod_selected(index: u32) -> bool {
+    let lod_node = visibility[inactive_words() + index];
+    let node = lod_node & ~LOD_REPRESENTATIVE;
+    let shift = (node % LOD_STATES_PER_WORD) * 2u;
+    let states = inactive_words() + uniforms.gaussian_count;
+    let state = (visibility[states + node / LOD_STATES_PER_WORD] >> shift) & 3u;
+    let wanted = select(LOD_STATE_OPEN, LOD_STATE_CUT, (lod_node & LOD_REPRESENTATIVE) != 0u);
+    return state == wanted;
+}
 
 @compute @workgroup_size(256)
 fn clear_sort(@builtin(global_invocation_id) global_id: vec3<u32>) {
@@ -58,9 +138,28 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
 
+    if is_inactive(index) {
+        return;
+    }
+
+    if !lod_selected(index) {
+        return;
+    }
+
     let gaussian = gaussians[index];
+    let object = objects[gaussian.object];
+
+    if object.visible == 0u {
+        return;
+    }
+
+    let model = object.model;
+    let world_pos = model * vec4<f32>(gaussian.position, 1.0);
 
-    let view_pos = uniforms.view * vec4<f32>(gaussian.position, 1.0);
+    if !passes_crop(world_pos.xyz) {
+        return;
+    }
+    let view_pos = uniforms.view * world_pos;
 
     if view_pos.z >= -0.1 {
         return;
@@ -74,7 +173,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     }
 
     let screen_x = (ndc.x * 0.5 + 0.5) * uniforms.viewport.x;
-    let screen_y = (ndc.y * -0.5 + 0.5) * uniforms.viewport.y;
+    let screen_y = (ndc.y * 0.5 + 0.5) * uniforms.viewport.y;
 
     let quat = gaussian.rotation;
     let r = quat.x;
@@ -88,7 +187,20 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         vec3<f32>(2.0 * (x * z + r * y), 2.0 * (y * z - r * x), 1.0 - 2.0 * (x * x + y * y)),
     );
 
-    let scale = exp(gaussian.scale_log);
+    let mip_splatting = uniforms.filter_mode == FILTER_MODE_MIP_SPLATTING;
+    var opacity_scale = 1.0;
+
+    var scale = exp(gaussian.scale_log);
+    if mip_splatting {
+        let scale_squared = scale * scale;
+        let filtered_squared = scale_squared + vec3<f32>(gaussian.filter_variance);
+        opacity_scale = sqrt(
+            (scale_squared.x * scale_squared.y * scale_squared.z)
+                / (filtered_squared.x * filtered_squared.y * filtered_squared.z),
+        );
+        scale = sqrt(filtered_squared);
+    }
+
     let scale_matrix = mat3x3<f32>(
         vec3<f32>(scale.x, 0.0, 0.0),
         vec3<f32>(0.0, scale.y, 0.0),
@@ -103,47 +215,78 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         uniforms.view[1].xyz,
         uniforms.view[2].xyz,
     );
+    let model3x3 = mat3x3<f32>(
+        model[0].xyz,
+        model[1].xyz,
+        model[2].xyz,
+    );
 
     let tz = view_pos.z;
     let focal_x = uniforms.focal.x;
     let focal_y = uniforms.focal.y;
 
-    let jacobian = mat3x3<f32>(
+    var jacobian = mat3x3<f32>(
         vec3<f32>(focal_x / tz, 0.0, 0.0),
         vec3<f32>(0.0, focal_y / tz, 0.0),
         vec3<f32>(-focal_x * view_pos.x / (tz * tz), -focal_y * view_pos.y / (tz * tz), 0.0),
     );
+    if uniforms.orthographic != 0u {
+        jacobian = mat3x3<f32>(
+            vec3<f32>(focal_x, 0.0, 0.0),
+            vec3<f32>(0.0, focal_y, 0.0),
+            vec3<f32>(0.0, 0.0, 0.0),
+        );
+    }
 
-    let t = jacobian * view3x3;
+    let t = jacobian * view3x3 * model3x3;
     let cov2d = t * sigma * transpose(t);
 
-    let cov_a = cov2d[0][0] + 0.3;
+    let dilation = select(LEGACY_DILATION, MIP_KERNEL_VARIANCE, mip_splatting);
+    let cov_a = cov2d[0][0] + dilation;
     let cov_b = cov2d[0][1];
-    let cov_d = cov2d[1][1] + 0.3;
+    let cov_d = cov2d[1][1] + dilation;
 
     let det = cov_a * cov_d - cov_b * cov_b;
     if det <= 0.0 {
         return;
     }
 
+    if mip_splatting {
+        let unfiltered_det = cov2d[0][0] * cov2d[1][1] - cov_b * cov_b;
+        opacity_scale *= sqrt(max(unfiltered_det / det, 0.000025));
+    }
+
     let det_inv = 1.0 / det;
     let conic = vec3<f32>(cov_d * det_inv, -cov_b * det_inv, cov_a * det_inv);
 
-    let mid = 0.5 * (cov_a + cov_d);
-    let discriminant = max(mid * mid - det, 0.0);
-    let lambda_max = mid + sqrt(discriminant);
-    let pixel_radius = ceil(3.0 * sqrt(lambda_max));
+    let opacity = opacity_scale / (1.0 + exp(-gaussian.opacity_logit));
 
-    if pixel_radius <= 0.0 || pixel_radius > 1024.0 {
+    if opacity < 1.0 / 255.0 {
         return;
     }
 
-    let opacity = 1.0 / (1.0 + exp(-gaussian.opacity_logit));
+    let extent = sqrt(2.0 * log(255.0 * opacity));
 
-    if opacity < 1.0 / 255.0 {
+    let mid = 0.5 * (cov_a + cov_d);
+    let discriminant = sqrt(max(mid * mid - det, 0.0));
+    let lambda_major = mid + discriminant;
+    let lambda_minor = max(mid - discriminant, 0.0);
+    let pixel_radius = ceil(extent * sqrt(lambda_major));
+
+    if pixel_radius <= 0.0 || pixel_radius > 1024.0 {
         return;
     }
 
+    var major_direction = vec2<f32>(1.0, 0.0);
+    if abs(cov_b) > 1e-6 {
+        major_direction = normalize(vec2<f32>(cov_b, lambda_major - cov_a));
+    } else if cov_d > cov_a {
+        major_direction = vec2<f32>(0.0, 1.0);
+    }
+    let minor_direction = vec2<f32>(-major_direction.y, major_direction.x);
+    let major_axis = major_direction * extent * sqrt(lambda_major);
+    let minor_axis = minor_direction * extent * sqrt(lambda_minor);
+
     let sh_c0 = 0.2820947917738781;
     let color = vec3<f32>(
         gaussian.sh_dc.x * sh_c0 + 0.5,
@@ -161,10 +304,12 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     sort_values[slot] = slot;
 
     splats[slot] = Splat2D(
-        vec4<f32>(clamped_color, 1.0),
+        clamped_color,
+        index,
         vec4<f32>(conic, opacity),
         vec2<f32>(screen_x, screen_y),
         pixel_radius,
-        0.0,
+        ndc.z,
+        vec4<f32>(major_axis, minor_axis),
     );
 }
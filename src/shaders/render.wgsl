@@ -1,9 +1,11 @@
 struct Splat2D {
-    color: vec4<f32>,
+    color: vec3<f32>,
+    index: u32,
     conic_and_opacity: vec4<f32>,
     center: vec2<f32>,
     radius: f32,
-    _pad: f32,
+    depth: f32,
+    axes: vec4<f32>,
 };
 
 struct Uniforms {
@@ -13,8 +15,12 @@ struct Uniforms {
     focal: vec2<f32>,
     gaussian_count: u32,
     padded_count: u32,
-    _pad1: u32,
-    _pad2: u32,
+    depth_alpha_threshold: f32,
+    filter_mode: u32,
+    render_mode: u32,
+    heatmap_max: f32,
+    orthographic: u32,
+    crop_count: u32,
 };
 
 struct VertexOutput {
@@ -27,6 +33,20 @@ struct VertexOutput {
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
 @group(0) @binding(1) var<storage, read> splats: array<Splat2D>;
 @group(0) @binding(2) var<storage, read> sort_values: array<u32>;
+@group(0) @binding(3) var<storage, read> selection: array<u32>;
+
+const SELECTION_TINT: vec3<f32> = vec3<f32>(1.0, 0.45, 0.1);
+const SELECTION_TINT_STRENGTH: f32 = 0.6;
+
+struct FragmentCounts {
+    width: u32,
+    height: u32,
+    _pad0: u32,
+    _pad1: u32,
+    counts: array<atomic<u32>>,
+};
+
+@group(1) @binding(0) var<storage, read_write> fragment_counts: FragmentCounts;
 
 const QUAD_VERTICES: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
     vec2<f32>(-1.0, -1.0),
@@ -46,7 +66,7 @@ fn vertex_main(
     let splat = splats[sorted_index];
 
     let quad_offset = QUAD_VERTICES[vertex_index % 6u];
-    let pixel_offset = quad_offset * splat.radius;
+    let pixel_offset = quad_offset.x * splat.axes.xy + quad_offset.y * splat.axes.zw;
 
     let screen_pos = splat.center + pixel_offset;
 
@@ -54,15 +74,20 @@ fn vertex_main(
     let ndc_y = screen_pos.y / uniforms.viewport.y * 2.0 - 1.0;
 
     var output: VertexOutput;
-    output.position = vec4<f32>(ndc_x, ndc_y, 0.0, 1.0);
-    output.splat_color = splat.color;
+    output.position = vec4<f32>(ndc_x, ndc_y, splat.depth, 1.0);
+    output.splat_color = vec4<f32>(splat.color, 1.0);
+    if (selection[splat.index / 32u] & (1u << (splat.index % 32u))) != 0u {
+        output.splat_color = vec4<f32>(
+            mix(splat.color, SELECTION_TINT, SELECTION_TINT_STRENGTH),
+            1.0,
+        );
+    }
     output.conic_and_opacity = splat.conic_and_opacity;
     output.offset = pixel_offset;
     return output;
 }
 
-@fragment
-fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+fn splat_alpha(input: VertexOutput) -> f32 {
     let offset = input.offset;
     let conic = input.conic_and_opacity.xyz;
     let opacity = input.conic_and_opacity.w;
@@ -70,10 +95,15 @@ fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
     let power = -0.5 * (conic.x * offset.x * offset.x + 2.0 * conic.y * offset.x * offset.y + conic.z * offset.y * offset.y);
 
     if power > 0.0 {
-        discard;
+        return 0.0;
     }
 
-    let alpha = min(0.99, opacity * exp(power));
+    return min(0.99, opacity * exp(power));
+}
+
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = splat_alpha(input);
 
     if alpha < 1.0 / 255.0 {
         discard;
@@ -81,3 +111,26 @@ fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
 
     return vec4<f32>(input.splat_color.rgb * alpha, alpha);
 }
+
+@fragment
+fn depth_main(input: VertexOutput) {
+    if splat_alpha(input) < uniforms.depth_alpha_threshold {
+        discard;
+    }
+}
+
+@fragment
+fn overdraw_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = splat_alpha(input);
+
+    if alpha < 1.0 / 255.0 {
+        discard;
+    }
+
+    let pixel = vec2<u32>(input.position.xy);
+    if pixel.x < fragment_counts.width && pixel.y < fragment_counts.height {
+        atomicAdd(&fragment_counts.counts[pixel.y * fragment_counts.width + pixel.x], 1u);
+    }
+
+    return vec4<f32>(1.0, 0.0, 0.0, alpha);
+}
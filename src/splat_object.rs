@@ -0,0 +1,103 @@
+use crate::cameras::TrainingCamera;
+use crate::gaussian::GpuGaussian;
+use nightshade::prelude::*;
+
+pub const MAX_SPLAT_OBJECTS: usize = 64;
+pub const SCENE_ASSET: usize = 0;
+
+pub struct SplatAsset {
+    pub name: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub path: std::path::PathBuf,
+    pub sh_degree: usize,
+    pub gaussians: Vec<GpuGaussian>,
+    pub training_cameras: Vec<TrainingCamera>,
+}
+
+#[derive(Copy, Clone)]
+pub struct GaussianSplat {
+    pub asset: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuSplatObject {
+    pub model: [[f32; 4]; 4],
+    pub visible: u32,
+    pub _pad: [u32; 3],
+}
+
+impl GpuSplatObject {
+    pub fn new(model: Mat4, visible: bool) -> Self {
+        Self {
+            model: model.into(),
+            visible: visible as u32,
+            _pad: [0; 3],
+        }
+    }
+}
+
+pub fn spawn_splat_objects(
+    world: &mut World,
+    assets: &[SplatAsset],
+) -> Vec<(Entity, GaussianSplat)> {
+    if assets.len() > MAX_SPLAT_OBJECTS {
+        eprintln!(
+            "Only the first {MAX_SPLAT_OBJECTS} of {} splat objects are rendered",
+            assets.len()
+        );
+    }
+    let count = assets.len().min(MAX_SPLAT_OBJECTS);
+    world
+        .spawn_entities(
+            LOCAL_TRANSFORM | GLOBAL_TRANSFORM | LOCAL_TRANSFORM_DIRTY | VISIBILITY,
+            count,
+        )
+        .into_iter()
+        .enumerate()
+        .map(|(asset, entity)| (entity, GaussianSplat { asset }))
+        .collect()
+}
+
+pub fn combined_gaussians(assets: &[SplatAsset]) -> Vec<GpuGaussian> {
+    assets
+        .iter()
+        .take(MAX_SPLAT_OBJECTS)
+        .enumerate()
+        .flat_map(|(object, asset)| {
+            asset.gaussians.iter().map(move |gaussian| GpuGaussian {
+                object: object as u32,
+                ..*gaussian
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn initial_objects(assets: &[SplatAsset], scene_model: Mat4) -> Vec<GpuSplatObject> {
+    (0..assets.len().min(MAX_SPLAT_OBJECTS))
+        .map(|asset| {
+            let model = if asset == SCENE_ASSET {
+                scene_model
+            } else {
+                Mat4::identity()
+            };
+            GpuSplatObject::new(model, true)
+        })
+        .collect()
+}
+
+pub fn gpu_objects(world: &World, objects: &[(Entity, GaussianSplat)]) -> Vec<GpuSplatObject> {
+    objects
+        .iter()
+        .map(|(entity, _)| {
+            let model = world
+                .get_global_transform(*entity)
+                .map_or(Mat4::identity(), |transform| transform.0);
+            let visible = world
+                .get_visibility(*entity)
+                .is_none_or(|visibility| visibility.visible);
+            GpuSplatObject::new(model, visible)
+        })
+        .collect()
+}
@@ -1,6 +1,11 @@
+use crate::crop::{GpuCropVolume, MAX_CROP_VOLUMES};
 use crate::gaussian::GpuGaussian;
+use crate::lod::{DEFAULT_LOD_PIXEL_SIZE, LOD_STATE_OPEN, LodTree};
+use crate::overdraw::{Overdraw, OverdrawStats};
+use crate::profiling::{GpuProfiler, Stage, StageTimings};
+use crate::splat_object::{GpuSplatObject, MAX_SPLAT_OBJECTS};
 use nightshade::ecs::camera::queries::query_active_camera_matrices;
-use nightshade::ecs::world::World;
+use nightshade::ecs::world::{Mat4, World};
 use nightshade::prelude::wgpu;
 use nightshade::render::wgpu::rendergraph::{PassExecutionContext, PassNode};
 use wgpu::util::DeviceExt;
@@ -11,7 +16,8 @@ const RENDER_SHADER: &str = include_str!("shaders/render.wgsl");
 
 const WORKGROUP_SIZE: u32 = 256;
 const SORT_UNIFORM_ALIGNMENT: u64 = 256;
-const SPLAT_SIZE: u64 = 48;
+const SPLAT_SIZE: u64 = 64;
+const LOD_CUT_INTERVAL: u32 = 4;
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -22,8 +28,12 @@ struct Uniforms {
     focal: [f32; 2],
     gaussian_count: u32,
     padded_count: u32,
-    _pad1: u32,
-    _pad2: u32,
+    depth_alpha_threshold: f32,
+    filter_mode: u32,
+    render_mode: u32,
+    heatmap_max: f32,
+    orthographic: u32,
+    crop_count: u32,
 }
 
 #[repr(C)]
@@ -44,28 +54,98 @@ struct DrawIndirect {
     first_instance: u32,
 }
 
+#[derive(Copy, Clone, PartialEq)]
+pub enum FilterMode {
+    Legacy,
+    MipSplatting,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    Color,
+    FragmentCount,
+    SaturatedFragments,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct SplatSettings {
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_alpha_threshold: f32,
+    pub filter_mode: FilterMode,
+    pub render_mode: RenderMode,
+    pub heatmap_max: f32,
+    pub lod_enabled: bool,
+    pub lod_pixel_size: f32,
+}
+
+impl Default for SplatSettings {
+    fn default() -> Self {
+        Self {
+            depth_test: false,
+            depth_write: false,
+            depth_alpha_threshold: 0.5,
+            filter_mode: FilterMode::Legacy,
+            render_mode: RenderMode::Color,
+            heatmap_max: 256.0,
+            lod_enabled: true,
+            lod_pixel_size: DEFAULT_LOD_PIXEL_SIZE,
+        }
+    }
+}
+
+pub struct SplatTarget<'a> {
+    pub color_view: &'a wgpu::TextureView,
+    pub color_ops: wgpu::Operations<wgpu::Color>,
+    pub depth_view: &'a wgpu::TextureView,
+    pub depth_ops: wgpu::Operations<f32>,
+    pub width: u32,
+    pub height: u32,
+}
+
 pub struct SplatPass {
     gaussian_count: u32,
     padded_count: u32,
-
-    _gaussian_buffer: wgpu::Buffer,
+    settings: SplatSettings,
+    objects: Vec<GpuSplatObject>,
+    crop_volumes: Vec<GpuCropVolume>,
+    pending_selection: Option<Vec<u32>>,
+    pending_inactive: Option<Vec<u32>>,
+    pending_gaussians: Vec<(usize, Vec<GpuGaussian>)>,
+    lod: Option<LodTree>,
+    lod_inputs: Vec<u8>,
+    lod_states: Vec<u32>,
+    lod_deferred_frames: u32,
+    lod_splat_count: Option<usize>,
+
+    gaussian_buffer: wgpu::Buffer,
     _splat_buffer: wgpu::Buffer,
     _sort_keys_buffer: wgpu::Buffer,
     _sort_values_buffer: wgpu::Buffer,
     draw_indirect_buffer: wgpu::Buffer,
     draw_indirect_reset_buffer: wgpu::Buffer,
     uniform_buffer: wgpu::Buffer,
+    crop_buffer: wgpu::Buffer,
+    object_buffer: wgpu::Buffer,
+    selection_buffer: wgpu::Buffer,
+    visibility_buffer: wgpu::Buffer,
+    lod_state_offset: u64,
 
     clear_sort_pipeline: wgpu::ComputePipeline,
     preprocess_pipeline: wgpu::ComputePipeline,
     sort_pipeline: wgpu::ComputePipeline,
     render_pipeline: wgpu::RenderPipeline,
+    depth_tested_render_pipeline: wgpu::RenderPipeline,
+    depth_write_pipeline: wgpu::RenderPipeline,
 
     preprocess_bind_group: wgpu::BindGroup,
     sort_bind_group: wgpu::BindGroup,
     render_bind_group: wgpu::BindGroup,
 
     sort_stages: Vec<SortStage>,
+
+    overdraw: Overdraw,
+    profiler: Option<GpuProfiler>,
 }
 
 struct SortStage {
@@ -76,15 +156,20 @@ impl SplatPass {
     pub fn new(
         device: &wgpu::Device,
         gaussians: &[GpuGaussian],
+        lod: Option<LodTree>,
         color_format: wgpu::TextureFormat,
     ) -> Self {
+        let gaussians = match &lod {
+            Some(lod) => [gaussians, lod.representatives()].concat(),
+            None => gaussians.to_vec(),
+        };
         let gaussian_count = gaussians.len() as u32;
         let padded_count = gaussian_count.next_power_of_two();
 
         let gaussian_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Gaussian Buffer"),
-            contents: bytemuck::cast_slice(gaussians),
-            usage: wgpu::BufferUsages::STORAGE,
+            contents: bytemuck::cast_slice(&gaussians),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
 
         let splat_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -140,6 +225,45 @@ impl SplatPass {
             mapped_at_creation: false,
         });
 
+        let crop_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Crop Volume Buffer"),
+            size: (std::mem::size_of::<GpuCropVolume>() * MAX_CROP_VOLUMES) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let object_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Splat Object Buffer"),
+            size: (std::mem::size_of::<GpuSplatObject>() * MAX_SPLAT_OBJECTS) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let selection_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Selection Buffer"),
+            size: 4 * gaussian_count.div_ceil(32).max(1) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let inactive_words = gaussian_count.div_ceil(32) as usize;
+        let lod_nodes = match &lod {
+            Some(lod) => lod.gaussian_nodes(),
+            None => vec![0; gaussians.len()],
+        };
+        let lod_states = match &lod {
+            Some(lod) => vec![0; lod.state_words()],
+            None => vec![LOD_STATE_OPEN],
+        };
+        let lod_state_offset = (4 * (inactive_words + lod_nodes.len())) as u64;
+        let visibility_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Gaussian Visibility Buffer"),
+            contents: bytemuck::cast_slice(
+                &[vec![0; inactive_words], lod_nodes, lod_states.clone()].concat(),
+            ),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
         let sort_stages = compute_sort_stages(padded_count);
         let sort_uniform_data = build_sort_uniform_data(padded_count, &sort_stages);
         let sort_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -192,6 +316,24 @@ impl SplatPass {
                         wgpu::BufferBindingType::Storage { read_only: false },
                         false,
                     ),
+                    buffer_layout_entry(
+                        6,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                        false,
+                    ),
+                    buffer_layout_entry(
+                        7,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                        false,
+                    ),
+                    buffer_layout_entry(
+                        8,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                        false,
+                    ),
                 ],
             });
 
@@ -226,7 +368,7 @@ impl SplatPass {
                 entries: &[
                     buffer_layout_entry(
                         0,
-                        wgpu::ShaderStages::VERTEX,
+                        wgpu::ShaderStages::VERTEX_FRAGMENT,
                         wgpu::BufferBindingType::Uniform,
                         false,
                     ),
@@ -242,6 +384,12 @@ impl SplatPass {
                         wgpu::BufferBindingType::Storage { read_only: true },
                         false,
                     ),
+                    buffer_layout_entry(
+                        3,
+                        wgpu::ShaderStages::VERTEX,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                        false,
+                    ),
                 ],
             });
 
@@ -273,6 +421,18 @@ impl SplatPass {
                     binding: 5,
                     resource: draw_indirect_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: crop_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: visibility_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: object_buffer.as_entire_binding(),
+                },
             ],
         });
 
@@ -318,6 +478,10 @@ impl SplatPass {
                     binding: 2,
                     resource: sort_values_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: selection_buffer.as_entire_binding(),
+                },
             ],
         });
 
@@ -385,138 +549,261 @@ impl SplatPass {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Splat Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &render_shader,
-                entry_point: Some("vertex_main"),
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &render_shader,
-                entry_point: Some("fragment_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: color_format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: false,
-                depth_compare: wgpu::CompareFunction::Always,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let render_pipeline = create_render_pipeline(
+            device,
+            &render_pipeline_layout,
+            &render_shader,
+            color_format,
+            RenderPipelineKind::Color { depth_test: false },
+        );
+
+        let depth_tested_render_pipeline = create_render_pipeline(
+            device,
+            &render_pipeline_layout,
+            &render_shader,
+            color_format,
+            RenderPipelineKind::Color { depth_test: true },
+        );
+
+        let depth_write_pipeline = create_render_pipeline(
+            device,
+            &render_pipeline_layout,
+            &render_shader,
+            color_format,
+            RenderPipelineKind::DepthWrite,
+        );
+
+        let overdraw = Overdraw::new(
+            device,
+            &render_bind_group_layout,
+            &render_shader,
+            color_format,
+        );
 
         Self {
             gaussian_count,
             padded_count,
-            _gaussian_buffer: gaussian_buffer,
+            settings: SplatSettings::default(),
+            objects: vec![GpuSplatObject::new(Mat4::identity(), true)],
+            crop_volumes: Vec::new(),
+            pending_selection: None,
+            pending_inactive: None,
+            pending_gaussians: Vec::new(),
+            lod,
+            lod_inputs: Vec::new(),
+            lod_states,
+            lod_deferred_frames: 0,
+            lod_splat_count: None,
+            gaussian_buffer,
             _splat_buffer: splat_buffer,
             _sort_keys_buffer: sort_keys_buffer,
             _sort_values_buffer: sort_values_buffer,
             draw_indirect_buffer,
             draw_indirect_reset_buffer,
             uniform_buffer,
+            crop_buffer,
+            object_buffer,
+            selection_buffer,
+            visibility_buffer,
+            lod_state_offset,
             clear_sort_pipeline,
             preprocess_pipeline,
             sort_pipeline,
             render_pipeline,
+            depth_tested_render_pipeline,
+            depth_write_pipeline,
             preprocess_bind_group,
             sort_bind_group,
             render_bind_group,
             sort_stages,
+            overdraw,
+            profiler: GpuProfiler::new(device),
         }
     }
-}
 
-impl PassNode<World> for SplatPass {
-    fn name(&self) -> &str {
-        "splat_pass"
+    pub fn set_settings(&mut self, settings: SplatSettings) {
+        self.settings = settings;
     }
 
-    fn reads(&self) -> Vec<&str> {
-        vec![]
+    pub fn set_objects(&mut self, mut objects: Vec<GpuSplatObject>) {
+        objects.truncate(MAX_SPLAT_OBJECTS);
+        self.objects = objects;
     }
 
-    fn writes(&self) -> Vec<&str> {
-        vec![]
+    pub fn set_crop_volumes(&mut self, mut volumes: Vec<GpuCropVolume>) {
+        volumes.truncate(MAX_CROP_VOLUMES);
+        self.crop_volumes = volumes;
     }
 
-    fn reads_writes(&self) -> Vec<&str> {
-        vec!["color", "depth"]
+    pub fn set_selection(&mut self, words: &[u32]) {
+        self.pending_selection = Some(words.to_vec());
     }
 
-    fn prepare(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, world: &World) {
-        let camera_matrices = match query_active_camera_matrices(world) {
-            Some(matrices) => matrices,
-            None => return,
+    pub fn set_inactive(&mut self, words: &[u32]) {
+        if let Some(lod) = &mut self.lod {
+            let changed = lod.set_inactive(words);
+            self.refit_lod(changed);
+        }
+        self.pending_inactive = Some(words.to_vec());
+    }
+
+    pub fn update_gaussians(&mut self, first: usize, gaussians: &[GpuGaussian]) {
+        self.pending_gaussians.push((first, gaussians.to_vec()));
+        if let Some(lod) = &mut self.lod {
+            let changed = lod.update_gaussians(first, gaussians);
+            self.refit_lod(changed);
+        }
+    }
+
+    fn refit_lod(&mut self, changed: Option<std::ops::Range<usize>>) {
+        let (Some(lod), Some(changed)) = (&self.lod, changed) else {
+            return;
         };
+        self.pending_gaussians.push((
+            lod.gaussian_count() + changed.start,
+            lod.representatives()[changed].to_vec(),
+        ));
+        self.lod_inputs.clear();
+    }
 
-        let view = camera_matrices.view;
-        let projection = camera_matrices.projection;
+    pub fn lod_splat_count(&self) -> Option<usize> {
+        self.lod_splat_count
+    }
 
-        let (viewport_width, viewport_height) = world
-            .resources
-            .window
-            .cached_viewport_size
-            .unwrap_or((1920, 1080));
+    pub fn overdraw_stats(&self) -> Option<OverdrawStats> {
+        self.overdraw.stats()
+    }
+
+    pub fn timings_supported(&self) -> bool {
+        self.profiler.is_some()
+    }
+
+    pub fn stage_timings(&self) -> Option<StageTimings> {
+        self.profiler.as_ref().and_then(GpuProfiler::average)
+    }
+
+    pub fn poll_timings(&mut self, queue: &wgpu::Queue) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.poll(queue.get_timestamp_period());
+        }
+    }
+
+    pub fn update_camera(
+        &mut self,
+        queue: &wgpu::Queue,
+        view: &Mat4,
+        projection: &Mat4,
+        viewport: (u32, u32),
+    ) {
+        self.overdraw.poll_stats();
+        self.poll_timings(queue);
+
+        let (viewport_width, viewport_height) = viewport;
 
         let focal_x = projection[(0, 0)] * viewport_width as f32 * 0.5;
         let focal_y = projection[(1, 1)] * viewport_height as f32 * 0.5;
 
         let uniforms = Uniforms {
-            view: view.into(),
-            projection: projection.into(),
+            view: (*view).into(),
+            projection: (*projection).into(),
             viewport: [viewport_width as f32, viewport_height as f32],
             focal: [focal_x, focal_y],
             gaussian_count: self.gaussian_count,
             padded_count: self.padded_count,
-            _pad1: 0,
-            _pad2: 0,
+            depth_alpha_threshold: self.settings.depth_alpha_threshold,
+            filter_mode: self.settings.filter_mode as u32,
+            render_mode: self.settings.render_mode as u32,
+            heatmap_max: self.settings.heatmap_max,
+            orthographic: (projection[(3, 3)] == 1.0) as u32,
+            crop_count: self.crop_volumes.len() as u32,
         };
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        if !self.objects.is_empty() {
+            queue.write_buffer(&self.object_buffer, 0, bytemuck::cast_slice(&self.objects));
+        }
+        if !self.crop_volumes.is_empty() {
+            queue.write_buffer(
+                &self.crop_buffer,
+                0,
+                bytemuck::cast_slice(&self.crop_volumes),
+            );
+        }
+        if let Some(words) = self.pending_selection.take() {
+            queue.write_buffer(&self.selection_buffer, 0, bytemuck::cast_slice(&words));
+        }
+        if let Some(mut words) = self.pending_inactive.take() {
+            words.truncate(self.gaussian_count.div_ceil(32) as usize);
+            queue.write_buffer(&self.visibility_buffer, 0, bytemuck::cast_slice(&words));
+        }
+        for (first, gaussians) in self.pending_gaussians.drain(..) {
+            queue.write_buffer(
+                &self.gaussian_buffer,
+                (first * std::mem::size_of::<GpuGaussian>()) as u64,
+                bytemuck::cast_slice(&gaussians),
+            );
+        }
+        self.update_lod(queue, view, projection, viewport_height);
     }
 
-    fn execute<'r, 'e>(
+    fn update_lod(
         &mut self,
-        context: PassExecutionContext<'r, 'e, World>,
-    ) -> nightshade::render::wgpu::rendergraph::Result<
-        Vec<nightshade::render::wgpu::rendergraph::SubGraphRunCommand<'r>>,
-    > {
+        queue: &wgpu::Queue,
+        view: &Mat4,
+        projection: &Mat4,
+        viewport_height: u32,
+    ) {
+        let Some(lod) = &self.lod else {
+            return;
+        };
+        let pixel_size = self
+            .settings
+            .lod_enabled
+            .then_some(self.settings.lod_pixel_size);
+        let inputs = [
+            bytemuck::cast_slice(view.as_slice()),
+            bytemuck::cast_slice(projection.as_slice()),
+            bytemuck::bytes_of(&viewport_height),
+            bytemuck::bytes_of(&pixel_size.unwrap_or(0.0)),
+            bytemuck::cast_slice(&self.objects),
+        ]
+        .concat();
+        if inputs == self.lod_inputs {
+            return;
+        }
+        if !self.lod_inputs.is_empty() && self.lod_deferred_frames + 1 < LOD_CUT_INTERVAL {
+            self.lod_deferred_frames += 1;
+            return;
+        }
+        self.lod_deferred_frames = 0;
+        self.lod_inputs = inputs;
+        let cut = lod.select(view, projection, viewport_height, &self.objects, pixel_size);
+        let changed = |(word, state): (usize, &u32)| self.lod_states[word] != *state;
+        if let (Some(first), Some(last)) = (
+            cut.states.iter().enumerate().position(changed),
+            cut.states.iter().enumerate().rposition(changed),
+        ) {
+            queue.write_buffer(
+                &self.visibility_buffer,
+                self.lod_state_offset + 4 * first as u64,
+                bytemuck::cast_slice(&cut.states[first..=last]),
+            );
+            self.lod_states = cut.states;
+        }
+        self.lod_splat_count = Some(cut.splat_count);
+    }
+
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: SplatTarget,
+    ) {
         if self.gaussian_count == 0 {
-            return Ok(context.into_sub_graph_commands());
+            return;
         }
 
-        context.encoder.copy_buffer_to_buffer(
+        encoder.copy_buffer_to_buffer(
             &self.draw_indirect_reset_buffer,
             0,
             &self.draw_indirect_buffer,
@@ -525,13 +812,13 @@ impl PassNode<World> for SplatPass {
         );
 
         {
-            let mut compute_pass =
-                context
-                    .encoder
-                    .begin_compute_pass(&wgpu::ComputePassDescriptor {
-                        label: Some("Clear Sort Pass"),
-                        timestamp_writes: None,
-                    });
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Clear Sort Pass"),
+                timestamp_writes: self
+                    .profiler
+                    .as_ref()
+                    .map(|profiler| profiler.compute_timestamp_writes(Stage::Clear, true, true)),
+            });
             compute_pass.set_pipeline(&self.clear_sort_pipeline);
             compute_pass.set_bind_group(0, &self.preprocess_bind_group, &[]);
             let workgroups = self.padded_count.div_ceil(WORKGROUP_SIZE);
@@ -539,13 +826,12 @@ impl PassNode<World> for SplatPass {
         }
 
         {
-            let mut compute_pass =
-                context
-                    .encoder
-                    .begin_compute_pass(&wgpu::ComputePassDescriptor {
-                        label: Some("Preprocess Pass"),
-                        timestamp_writes: None,
-                    });
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Preprocess Pass"),
+                timestamp_writes: self.profiler.as_ref().map(|profiler| {
+                    profiler.compute_timestamp_writes(Stage::Preprocess, true, true)
+                }),
+            });
             compute_pass.set_pipeline(&self.preprocess_pipeline);
             compute_pass.set_bind_group(0, &self.preprocess_bind_group, &[]);
             let workgroups = self.gaussian_count.div_ceil(WORKGROUP_SIZE);
@@ -555,58 +841,158 @@ impl PassNode<World> for SplatPass {
         let num_pairs = self.padded_count / 2;
         let sort_workgroups = num_pairs.div_ceil(WORKGROUP_SIZE);
 
-        for stage in &self.sort_stages {
-            let mut compute_pass =
-                context
-                    .encoder
-                    .begin_compute_pass(&wgpu::ComputePassDescriptor {
-                        label: Some("Sort Pass"),
-                        timestamp_writes: None,
-                    });
+        let last_sort_stage = self.sort_stages.len().saturating_sub(1);
+        for (stage_index, stage) in self.sort_stages.iter().enumerate() {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Sort Pass"),
+                timestamp_writes: self.profiler.as_ref().map(|profiler| {
+                    profiler.compute_timestamp_writes(
+                        Stage::Sort,
+                        stage_index == 0,
+                        stage_index == last_sort_stage,
+                    )
+                }),
+            });
             compute_pass.set_pipeline(&self.sort_pipeline);
             compute_pass.set_bind_group(0, &self.sort_bind_group, &[stage.dynamic_offset]);
             compute_pass.dispatch_workgroups(sort_workgroups, 1, 1);
         }
 
-        let (color_view, color_load, color_store) = context.get_color_attachment("color")?;
-        let (depth_view, depth_load, depth_store) = context.get_depth_attachment("depth")?;
+        if self.settings.render_mode != RenderMode::Color {
+            self.overdraw.ensure_target(
+                device,
+                queue,
+                &self.uniform_buffer,
+                target.width,
+                target.height,
+            );
+            self.overdraw.encode(
+                encoder,
+                &self.render_bind_group,
+                &self.draw_indirect_buffer,
+                target.color_view,
+                target.color_ops,
+                self.profiler.as_ref(),
+            );
+            if let Some(profiler) = &self.profiler {
+                profiler.resolve(encoder);
+            }
+            return;
+        }
 
         {
-            let mut render_pass = context
-                .encoder
-                .begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Splat Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: color_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: color_load,
-                            store: color_store,
-                        },
-                        depth_slice: None,
-                    })],
-                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: depth_view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: depth_load,
-                            store: depth_store,
-                        }),
-                        stencil_ops: None,
-                    }),
-                    timestamp_writes: None,
-                    occlusion_query_set: None,
-                });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Splat Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target.color_view,
+                    resolve_target: None,
+                    ops: target.color_ops,
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: target.depth_view,
+                    depth_ops: Some(target.depth_ops),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: self
+                    .profiler
+                    .as_ref()
+                    .map(|profiler| profiler.render_timestamp_writes(Stage::Render, true, true)),
+                occlusion_query_set: None,
+            });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            if self.settings.depth_test {
+                render_pass.set_pipeline(&self.depth_tested_render_pipeline);
+            } else {
+                render_pass.set_pipeline(&self.render_pipeline);
+            }
             render_pass.set_bind_group(0, &self.render_bind_group, &[]);
             render_pass.draw_indirect(&self.draw_indirect_buffer, 0);
+
+            if self.settings.depth_write {
+                render_pass.set_pipeline(&self.depth_write_pipeline);
+                render_pass.draw_indirect(&self.draw_indirect_buffer, 0);
+            }
+        }
+
+        if let Some(profiler) = &self.profiler {
+            profiler.resolve(encoder);
         }
+    }
+}
+
+impl PassNode<World> for SplatPass {
+    fn name(&self) -> &str {
+        "splat_pass"
+    }
+
+    fn reads(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn writes(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn reads_writes(&self) -> Vec<&str> {
+        vec!["color", "depth"]
+    }
+
+    fn prepare(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, world: &World) {
+        let camera_matrices = match query_active_camera_matrices(world) {
+            Some(matrices) => matrices,
+            None => return,
+        };
+
+        let viewport = world
+            .resources
+            .window
+            .cached_viewport_size
+            .unwrap_or((1920, 1080));
+
+        self.update_camera(
+            queue,
+            &camera_matrices.view,
+            &camera_matrices.projection,
+            viewport,
+        );
+    }
+
+    fn execute<'r, 'e>(
+        &mut self,
+        context: PassExecutionContext<'r, 'e, World>,
+    ) -> nightshade::render::wgpu::rendergraph::Result<
+        Vec<nightshade::render::wgpu::rendergraph::SubGraphRunCommand<'r>>,
+    > {
+        let (color_view, color_load, color_store) = context.get_color_attachment("color")?;
+        let (depth_view, depth_load, depth_store) = context.get_depth_attachment("depth")?;
+        let (width, height) = context.get_texture_size("color")?;
+
+        self.encode(
+            context.device,
+            context.queue,
+            context.encoder,
+            SplatTarget {
+                color_view,
+                color_ops: wgpu::Operations {
+                    load: color_load,
+                    store: color_store,
+                },
+                depth_view,
+                depth_ops: wgpu::Operations {
+                    load: depth_load,
+                    store: depth_store,
+                },
+                width,
+                height,
+            },
+        );
 
         Ok(context.into_sub_graph_commands())
     }
 }
 
-fn buffer_layout_entry(
+pub fn buffer_layout_entry(
     binding: u32,
     visibility: wgpu::ShaderStages,
     buffer_type: wgpu::BufferBindingType,
@@ -624,6 +1010,100 @@ fn buffer_layout_entry(
     }
 }
 
+pub enum RenderPipelineKind {
+    Color { depth_test: bool },
+    DepthWrite,
+    FragmentCount,
+}
+
+pub fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    color_format: wgpu::TextureFormat,
+    kind: RenderPipelineKind,
+) -> wgpu::RenderPipeline {
+    let (label, fragment_entry_point, write_mask, depth_state) = match kind {
+        RenderPipelineKind::Color { depth_test: false } => (
+            "Splat Render Pipeline",
+            "fragment_main",
+            wgpu::ColorWrites::ALL,
+            Some((false, wgpu::CompareFunction::Always)),
+        ),
+        RenderPipelineKind::Color { depth_test: true } => (
+            "Splat Depth Tested Render Pipeline",
+            "fragment_main",
+            wgpu::ColorWrites::ALL,
+            Some((false, wgpu::CompareFunction::GreaterEqual)),
+        ),
+        RenderPipelineKind::DepthWrite => (
+            "Splat Depth Write Pipeline",
+            "depth_main",
+            wgpu::ColorWrites::empty(),
+            Some((true, wgpu::CompareFunction::GreaterEqual)),
+        ),
+        RenderPipelineKind::FragmentCount => (
+            "Splat Fragment Count Pipeline",
+            "overdraw_main",
+            wgpu::ColorWrites::ALL,
+            None,
+        ),
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vertex_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fragment_entry_point),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: depth_state.map(|(depth_write_enabled, depth_compare)| {
+            wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
 fn compute_sort_stages(padded_count: u32) -> Vec<SortStage> {
     if padded_count <= 1 {
         return vec![];
@@ -0,0 +1,457 @@
+use crate::bounds::SceneBounds;
+use crate::gaussian::GpuGaussian;
+use bytemuck::Zeroable;
+use nightshade::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, mpsc};
+
+pub const CHUNK_EXTENSION: &str = "chunks";
+pub const DEFAULT_CHUNK_SIZE: usize = 65_536;
+pub const DEFAULT_GPU_BUDGET_MB: usize = 256;
+
+const MAGIC: &[u8; 8] = b"GSCHUNK1";
+const STREAMED_BYTES_PER_GAUSSIAN: usize = 136;
+const LOADER_THREADS: usize = 2;
+const MAX_PENDING_LOADS: usize = 8;
+const MAX_UPLOADS_PER_FRAME: usize = 2;
+const EMPTY_OPACITY_LOGIT: f32 = -30.0;
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct ChunkInfo {
+    first: u64,
+    count: u32,
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl ChunkInfo {
+    fn distance(&self, point: &Vec3) -> f32 {
+        let min = Vec3::from(self.min);
+        let max = Vec3::from(self.max);
+        (min - point).sup(&(point - max)).sup(&Vec3::zeros()).norm()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkHeader {
+    gaussian_count: u64,
+    chunk_size: u32,
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    chunks: Vec<ChunkInfo>,
+}
+
+pub struct ChunkFile {
+    path: PathBuf,
+    header: ChunkHeader,
+    data_offset: u64,
+}
+
+impl ChunkFile {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = std::fs::File::open(path)?;
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(format!("{} is not a chunked splat file", path.display()).into());
+        }
+        let mut length = [0u8; 8];
+        file.read_exact(&mut length)?;
+        let length = u64::from_le_bytes(length);
+        let mut header = vec![0u8; length as usize];
+        file.read_exact(&mut header)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            header: serde_json::from_slice(&header)?,
+            data_offset: (MAGIC.len() + 8) as u64 + length,
+        })
+    }
+
+    pub fn gaussian_count(&self) -> usize {
+        self.header.gaussian_count as usize
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.header.chunks.len()
+    }
+
+    pub fn bounds(&self) -> SceneBounds {
+        SceneBounds {
+            min: Vec3::from(self.header.bounds_min),
+            max: Vec3::from(self.header.bounds_max),
+        }
+    }
+}
+
+pub fn is_chunk_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == CHUNK_EXTENSION)
+}
+
+pub fn write_chunk_file(
+    path: &Path,
+    mut gaussians: Vec<GpuGaussian>,
+    chunk_size: usize,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let bounds = SceneBounds::from_gaussians(&gaussians).unwrap_or(SceneBounds {
+        min: Vec3::zeros(),
+        max: Vec3::zeros(),
+    });
+    let mut ranges = Vec::new();
+    split_chunks(&mut gaussians, 0, chunk_size.max(1), &mut ranges);
+    let chunks: Vec<ChunkInfo> = ranges
+        .into_iter()
+        .map(|range: std::ops::Range<usize>| {
+            let (min, max) = gaussians[range.clone()].iter().fold(
+                (Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)),
+                |(min, max), gaussian| {
+                    let position = Vec3::from(gaussian.position);
+                    (min.inf(&position), max.sup(&position))
+                },
+            );
+            ChunkInfo {
+                first: range.start as u64,
+                count: range.len() as u32,
+                min: min.into(),
+                max: max.into(),
+            }
+        })
+        .collect();
+    let chunk_count = chunks.len();
+    let header = serde_json::to_vec(&ChunkHeader {
+        gaussian_count: gaussians.len() as u64,
+        chunk_size: chunk_size.max(1) as u32,
+        bounds_min: bounds.min.into(),
+        bounds_max: bounds.max.into(),
+        chunks,
+    })?;
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&(header.len() as u64).to_le_bytes())?;
+    file.write_all(&header)?;
+    file.write_all(bytemuck::cast_slice(&gaussians))?;
+    file.flush()?;
+    Ok(chunk_count)
+}
+
+fn split_chunks(
+    gaussians: &mut [GpuGaussian],
+    first: usize,
+    chunk_size: usize,
+    ranges: &mut Vec<std::ops::Range<usize>>,
+) {
+    if gaussians.len() <= chunk_size {
+        if !gaussians.is_empty() {
+            ranges.push(first..first + gaussians.len());
+        }
+        return;
+    }
+    let (min, max) = gaussians.iter().fold(
+        (Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)),
+        |(min, max), gaussian| {
+            let position = Vec3::from(gaussian.position);
+            (min.inf(&position), max.sup(&position))
+        },
+    );
+    let axis = (max - min).imax();
+    let split = gaussians.len().div_ceil(chunk_size) / 2 * chunk_size;
+    gaussians.select_nth_unstable_by(split, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    let (near, far) = gaussians.split_at_mut(split);
+    split_chunks(near, first, chunk_size, ranges);
+    split_chunks(far, first + split, chunk_size, ranges);
+}
+
+pub fn empty_gaussian() -> GpuGaussian {
+    GpuGaussian {
+        opacity_logit: EMPTY_OPACITY_LOGIT,
+        ..GpuGaussian::zeroed()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct StreamingStats {
+    pub resident: usize,
+    pub loading: usize,
+    pub chunks: usize,
+    pub slots: usize,
+}
+
+pub struct ChunkStreamer {
+    chunks: Vec<ChunkInfo>,
+    chunk_size: usize,
+    slots: Vec<Option<usize>>,
+    chunk_slots: Vec<Option<usize>>,
+    loading: Vec<bool>,
+    ready: Vec<(usize, Vec<GpuGaussian>)>,
+    requests: Sender<usize>,
+    loaded: Receiver<(usize, std::io::Result<Vec<GpuGaussian>>)>,
+}
+
+impl ChunkStreamer {
+    pub fn new(file: ChunkFile, budget_bytes: usize, max_gaussians: usize) -> Self {
+        let chunk_size = file.header.chunk_size as usize;
+        let budget_gaussians = (budget_bytes / STREAMED_BYTES_PER_GAUSSIAN).min(max_gaussians);
+        let slot_count = (budget_gaussians / chunk_size).clamp(1, file.chunk_count().max(1));
+        let (requests, request_receiver) = mpsc::channel::<usize>();
+        let (loaded_sender, loaded) = mpsc::channel();
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+        let chunks = Arc::new(file.header.chunks.clone());
+        for _ in 0..LOADER_THREADS {
+            let request_receiver = request_receiver.clone();
+            let loaded_sender = loaded_sender.clone();
+            let chunks = chunks.clone();
+            let path = file.path.clone();
+            let data_offset = file.data_offset;
+            std::thread::spawn(move || {
+                let mut file = match std::fs::File::open(&path) {
+                    Ok(file) => file,
+                    Err(error) => {
+                        eprintln!("Failed to open {}: {error}", path.display());
+                        return;
+                    }
+                };
+                loop {
+                    let Ok(chunk) = request_receiver.lock().unwrap().recv() else {
+                        return;
+                    };
+                    let info = chunks[chunk];
+                    let mut gaussians = vec![GpuGaussian::zeroed(); info.count as usize];
+                    let offset =
+                        data_offset + info.first * std::mem::size_of::<GpuGaussian>() as u64;
+                    let result = file
+                        .seek(SeekFrom::Start(offset))
+                        .and_then(|_| file.read_exact(bytemuck::cast_slice_mut(&mut gaussians)))
+                        .map(|_| gaussians);
+                    if loaded_sender.send((chunk, result)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        Self {
+            chunk_slots: vec![None; file.chunk_count()],
+            loading: vec![false; file.chunk_count()],
+            chunks: file.header.chunks,
+            chunk_size,
+            slots: vec![None; slot_count],
+            ready: Vec::new(),
+            requests,
+            loaded,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len() * self.chunk_size
+    }
+
+    pub fn stats(&self) -> StreamingStats {
+        StreamingStats {
+            resident: self.slots.iter().flatten().count(),
+            loading: self.loading.iter().filter(|loading| **loading).count(),
+            chunks: self.chunks.len(),
+            slots: self.slots.len(),
+        }
+    }
+
+    pub fn update(&mut self, camera_position: Vec3) -> Vec<(usize, Vec<GpuGaussian>)> {
+        for (chunk, result) in self.loaded.try_iter() {
+            self.loading[chunk] = false;
+            match result {
+                Ok(gaussians) => self.ready.push((chunk, gaussians)),
+                Err(error) => eprintln!("Failed to read chunk {chunk}: {error}"),
+            }
+        }
+
+        let distances: Vec<f32> = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.distance(&camera_position))
+            .collect();
+        let mut order: Vec<usize> = (0..self.chunks.len()).collect();
+        order.sort_by(|a, b| distances[*a].total_cmp(&distances[*b]));
+        let mut wanted = vec![false; self.chunks.len()];
+        for &chunk in order.iter().take(self.slots.len()) {
+            wanted[chunk] = true;
+        }
+
+        self.ready.retain(|(chunk, _)| wanted[*chunk]);
+        self.ready
+            .sort_by(|(a, _), (b, _)| distances[*b].total_cmp(&distances[*a]));
+        let mut uploads = Vec::new();
+        while uploads.len() < MAX_UPLOADS_PER_FRAME {
+            let Some(slot) = self.free_slot(&wanted, &distances) else {
+                break;
+            };
+            let Some((chunk, mut gaussians)) = self.ready.pop() else {
+                break;
+            };
+            if let Some(evicted) = self.slots[slot].replace(chunk) {
+                self.chunk_slots[evicted] = None;
+            }
+            self.chunk_slots[chunk] = Some(slot);
+            gaussians.resize(self.chunk_size, empty_gaussian());
+            uploads.push((slot * self.chunk_size, gaussians));
+        }
+
+        let mut pending =
+            self.loading.iter().filter(|loading| **loading).count() + self.ready.len();
+        for &chunk in order.iter().take(self.slots.len()) {
+            if pending >= MAX_PENDING_LOADS {
+                break;
+            }
+            let queued = self.ready.iter().any(|(ready, _)| *ready == chunk);
+            if self.chunk_slots[chunk].is_some() || self.loading[chunk] || queued {
+                continue;
+            }
+            if self.requests.send(chunk).is_ok() {
+                self.loading[chunk] = true;
+                pending += 1;
+            }
+        }
+        uploads
+    }
+
+    fn free_slot(&self, wanted: &[bool], distances: &[f32]) -> Option<usize> {
+        if let Some(slot) = self.slots.iter().position(Option::is_none) {
+            return Some(slot);
+        }
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, chunk)| chunk.map(|chunk| (slot, chunk)))
+            .filter(|(_, chunk)| !wanted[*chunk])
+            .max_by(|(_, a), (_, b)| distances[*a].total_cmp(&distances[*b]))
+            .map(|(slot, _)| slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK_SIZE: usize = 16;
+    const CHUNKS: usize = 4;
+
+    fn line_gaussians() -> Vec<GpuGaussian> {
+        (0..CHUNK_SIZE * CHUNKS)
+            .map(|index| GpuGaussian {
+                position: [index as f32, (index % 3) as f32, 0.0],
+                opacity_logit: index as f32 * 0.01,
+                ..GpuGaussian::zeroed()
+            })
+            .rev()
+            .collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "gaussian_splats_{}_{name}.{CHUNK_EXTENSION}",
+            std::process::id()
+        ))
+    }
+
+    fn read_chunk(file: &ChunkFile, chunk: usize) -> Vec<GpuGaussian> {
+        let info = file.header.chunks[chunk];
+        let bytes = std::fs::read(&file.path).expect("Failed to read chunk file");
+        let start = file.data_offset as usize + info.first as usize * size_of::<GpuGaussian>();
+        let end = start + info.count as usize * size_of::<GpuGaussian>();
+        bytemuck::pod_collect_to_vec(&bytes[start..end])
+    }
+
+    fn settle(streamer: &mut ChunkStreamer, camera: Vec3, wanted: &[usize]) {
+        for _ in 0..1000 {
+            streamer.update(camera);
+            let done = wanted
+                .iter()
+                .all(|chunk| streamer.chunk_slots[*chunk].is_some());
+            if done && streamer.stats().loading == 0 {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        panic!("chunks {wanted:?} never became resident");
+    }
+
+    #[test]
+    fn chunk_file_round_trips() {
+        let gaussians = line_gaussians();
+        let path = temp_path("round_trip");
+        let chunks =
+            write_chunk_file(&path, gaussians.clone(), CHUNK_SIZE).expect("Failed to write");
+        let file = ChunkFile::open(&path).expect("Failed to open");
+
+        assert_eq!(chunks, CHUNKS);
+        assert_eq!(file.chunk_count(), CHUNKS);
+        assert_eq!(file.gaussian_count(), gaussians.len());
+        let bounds = SceneBounds::from_gaussians(&gaussians).expect("gaussians have bounds");
+        assert_eq!(file.bounds().min, bounds.min);
+        assert_eq!(file.bounds().max, bounds.max);
+
+        let mut read_back = Vec::new();
+        for chunk in 0..file.chunk_count() {
+            let info = file.header.chunks[chunk];
+            assert_eq!(info.count as usize, CHUNK_SIZE);
+            let chunk_gaussians = read_chunk(&file, chunk);
+            for gaussian in &chunk_gaussians {
+                let position = Vec3::from(gaussian.position);
+                assert!(info.distance(&position) == 0.0);
+            }
+            read_back.extend(chunk_gaussians);
+        }
+        std::fs::remove_file(&path).ok();
+
+        let key = |gaussian: &GpuGaussian| gaussian.position[0].to_bits();
+        let mut expected = gaussians;
+        expected.sort_by_key(key);
+        read_back.sort_by_key(key);
+        assert!(
+            bytemuck::cast_slice::<GpuGaussian, u8>(&expected)
+                == bytemuck::cast_slice::<GpuGaussian, u8>(&read_back)
+        );
+        assert!(ChunkFile::open(&std::env::temp_dir().join("missing.chunks")).is_err());
+    }
+
+    #[test]
+    fn streamer_fills_nearest_chunks_and_evicts_unwanted() {
+        let path = temp_path("streamer");
+        write_chunk_file(&path, line_gaussians(), CHUNK_SIZE).expect("Failed to write");
+        let file = ChunkFile::open(&path).expect("Failed to open");
+        let chunk_at = |x: f32| {
+            file.header
+                .chunks
+                .iter()
+                .position(|info| info.min[0] <= x && x <= info.max[0])
+                .expect("no chunk covers x")
+        };
+        let (first, second, third) = (chunk_at(0.0), chunk_at(20.0), chunk_at(40.0));
+        let budget = 2 * CHUNK_SIZE * STREAMED_BYTES_PER_GAUSSIAN;
+        let mut streamer = ChunkStreamer::new(file, budget, usize::MAX);
+        assert_eq!(streamer.capacity(), 2 * CHUNK_SIZE);
+
+        let start = Vec3::new(-10.0, 0.0, 0.0);
+        settle(&mut streamer, start, &[first, second]);
+        let slots = streamer.slots.clone();
+        let mut chunks: Vec<usize> = slots.iter().flatten().copied().collect();
+        chunks.sort();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(chunks, expected);
+        assert_eq!(streamer.stats().resident, 2);
+        assert!(streamer.loading.iter().all(|loading| !loading));
+
+        let second_slot = streamer.chunk_slots[second];
+        let middle = Vec3::new(30.0, 0.0, 0.0);
+        settle(&mut streamer, middle, &[second, third]);
+        assert_eq!(streamer.chunk_slots[second], second_slot);
+        assert_eq!(streamer.chunk_slots[first], None);
+        let first_slot = slots
+            .iter()
+            .position(|chunk| *chunk == Some(first))
+            .expect("first chunk had a slot");
+        assert_eq!(streamer.slots[first_slot], Some(third));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,925 @@
+use crate::camera_path::{self, CameraKeyframe, CameraPath};
+use crate::crop::{self, CropMode, CropShape, CropVolume};
+use crate::edits::GaussianState;
+use crate::filter;
+use crate::gizmo::GizmoMode;
+use crate::scene_transform::{self, SceneTransform};
+use crate::selection::SelectionMode;
+use crate::splat_object::SCENE_ASSET;
+use crate::splat_pass::{FilterMode, RenderMode};
+use crate::{
+    BOOKMARK_KEYS, DEFAULT_MAX_ASPECT_RATIO, DEFAULT_MAX_SCALE_RADII, DEFAULT_MIN_OPACITY,
+    DEFAULT_OUTLIER_STD_RATIO, GaussianSplatViewer, KEYFRAME_SPACING_SECONDS, ProjectionMode,
+    TOP_VIEW_PITCH, TURNTABLE_SECONDS, current_camera_pose,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{decimate, edits, ply};
+use nightshade::prelude::*;
+
+impl GaussianSplatViewer {
+    fn translation_speed(&self) -> f32 {
+        self.scene_bounds
+            .map_or(0.01, |bounds| bounds.radius() * 0.005)
+    }
+
+    pub fn selection_overlay(&self, ui_context: &egui::Context) {
+        if let Some(drag) = &self.selection_drag {
+            let scale = ui_context.pixels_per_point();
+            let points: Vec<egui::Pos2> = drag
+                .points
+                .iter()
+                .map(|point| egui::pos2(point.x / scale, point.y / scale))
+                .collect();
+            let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 140, 40));
+            let painter = ui_context.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("selection_overlay"),
+            ));
+            match self.selection_mode {
+                Some(SelectionMode::Rectangle) if points.len() == 2 => {
+                    painter.rect_stroke(
+                        egui::Rect::from_two_pos(points[0], points[1]),
+                        0.0,
+                        stroke,
+                        egui::StrokeKind::Middle,
+                    );
+                }
+                Some(SelectionMode::Lasso) if points.len() >= 2 => {
+                    painter.add(egui::Shape::closed_line(points, stroke));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn navigation_panel(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        ui.label(format!("Gaussians: {}", self.gaussian_count));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(stats) = self.streaming_stats {
+            ui.label(format!(
+                "Streaming: {} of {} chunks resident in {} slots, {} loading",
+                stats.resident, stats.chunks, stats.slots, stats.loading
+            ));
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Frame scene (F)").clicked() {
+                self.frame_scene(world);
+            }
+            let flying = self.fly_camera.is_some();
+            if ui.radio(!flying, "Orbit").clicked() {
+                self.set_fly_mode(world, false);
+            }
+            if ui.radio(flying, "Fly (V)").clicked() {
+                self.set_fly_mode(world, true);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Projection:");
+            let mut projection_mode = self.projection_mode;
+            ui.radio_value(
+                &mut projection_mode,
+                ProjectionMode::Perspective,
+                "Perspective",
+            );
+            ui.radio_value(
+                &mut projection_mode,
+                ProjectionMode::Orthographic,
+                "Orthographic (O)",
+            );
+            self.set_projection_mode(world, projection_mode);
+        });
+        let mut fov_degrees = self.y_fov_rad.to_degrees();
+        if ui
+            .add(egui::Slider::new(&mut fov_degrees, 10.0..=120.0).text("Field of view"))
+            .changed()
+        {
+            self.y_fov_rad = fov_degrees.to_radians();
+        }
+        ui.horizontal(|ui| {
+            ui.label("View:");
+            if ui.button("Top").clicked() {
+                self.set_orbit_angles(world, 0.0, TOP_VIEW_PITCH);
+            }
+            if ui.button("Front").clicked() {
+                self.set_orbit_angles(world, 0.0, 0.0);
+            }
+            if ui.button("Side").clicked() {
+                self.set_orbit_angles(world, std::f32::consts::FRAC_PI_2, 0.0);
+            }
+        });
+        if let Some(fly_camera) = &mut self.fly_camera {
+            ui.add(
+                egui::Slider::new(&mut fly_camera.speed, 0.001..=1000.0)
+                    .logarithmic(true)
+                    .text("Fly speed"),
+            );
+        }
+    }
+
+    pub fn timings_panel(&self, ui: &mut egui::Ui, world: &World) {
+        let fps = 1.0 / world.resources.window.timing.delta_time.max(0.001);
+        ui.horizontal(|ui| {
+            ui.label(format!("FPS: {:.1}", fps));
+            match self.gpu_timings {
+                Some(timings) => {
+                    ui.label(format!("GPU: {:.2} ms", timings.total_ms()));
+                }
+                None if self.gpu_timings_supported => {
+                    ui.label("GPU: waiting for timings");
+                }
+                None => {
+                    ui.label(
+                        "GPU timings unavailable (TIMESTAMP_QUERY not enabled on this device)",
+                    );
+                }
+            }
+        });
+        if let Some(timings) = self.gpu_timings {
+            egui::Grid::new("gpu_timings").show(ui, |ui| {
+                for (stage, milliseconds) in [
+                    ("Clear", timings.clear_ms),
+                    ("Preprocess", timings.preprocess_ms),
+                    ("Sort", timings.sort_ms),
+                    ("Render", timings.render_ms),
+                ] {
+                    ui.label(stage);
+                    ui.label(format!("{:.3} ms", milliseconds));
+                    ui.end_row();
+                }
+            });
+        }
+    }
+
+    pub fn anti_aliasing_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Anti-aliasing:");
+            ui.radio_value(
+                &mut self.splat_settings.filter_mode,
+                FilterMode::Legacy,
+                "Legacy 0.3 px",
+            );
+            ui.radio_value(
+                &mut self.splat_settings.filter_mode,
+                FilterMode::MipSplatting,
+                "Mip-Splatting",
+            );
+        });
+        if self.splat_settings.filter_mode == FilterMode::MipSplatting
+            && self.training_cameras.is_empty()
+        {
+            ui.label("3D filter unavailable: no cameras.json found");
+        }
+    }
+
+    pub fn lod_panel(&mut self, ui: &mut egui::Ui) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let lod_available = self.streamer.is_none();
+        #[cfg(target_arch = "wasm32")]
+        let lod_available = true;
+        ui.add_enabled(
+            lod_available,
+            egui::Checkbox::new(&mut self.splat_settings.lod_enabled, "Level of detail"),
+        );
+        if !lod_available {
+            ui.label("LOD unavailable while streaming");
+        }
+        ui.add_enabled(
+            lod_available && self.splat_settings.lod_enabled,
+            egui::Slider::new(&mut self.splat_settings.lod_pixel_size, 0.5..=32.0)
+                .logarithmic(true)
+                .text("LOD node size (px)"),
+        );
+        if let Some(count) = self.lod_splat_count {
+            ui.label(format!("LOD splats: {count}"));
+        }
+    }
+
+    pub fn depth_panel(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(
+            &mut self.splat_settings.depth_test,
+            "Depth test against scene",
+        );
+        ui.checkbox(&mut self.splat_settings.depth_write, "Write splat depth");
+        ui.add_enabled(
+            self.splat_settings.depth_write,
+            egui::Slider::new(&mut self.splat_settings.depth_alpha_threshold, 0.01..=0.99)
+                .text("Depth alpha threshold"),
+        );
+    }
+
+    pub fn render_mode_panel(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Render mode")
+            .selected_text(match self.splat_settings.render_mode {
+                RenderMode::Color => "Color",
+                RenderMode::FragmentCount => "Fragment count",
+                RenderMode::SaturatedFragments => "Fragments after saturation",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut self.splat_settings.render_mode,
+                    RenderMode::Color,
+                    "Color",
+                );
+                ui.selectable_value(
+                    &mut self.splat_settings.render_mode,
+                    RenderMode::FragmentCount,
+                    "Fragment count",
+                );
+                ui.selectable_value(
+                    &mut self.splat_settings.render_mode,
+                    RenderMode::SaturatedFragments,
+                    "Fragments after saturation",
+                );
+            });
+        if self.splat_settings.render_mode != RenderMode::Color {
+            ui.add(
+                egui::Slider::new(&mut self.splat_settings.heatmap_max, 1.0..=4096.0)
+                    .logarithmic(true)
+                    .text("Heat-map max"),
+            );
+            if let Some(stats) = self.overdraw_stats {
+                let covered_pixels = stats.covered_pixels.max(1) as f32;
+                ui.label(format!("Fragments: {}", stats.total_fragments));
+                ui.label(format!(
+                    "After saturation: {} ({:.1}%)",
+                    stats.saturated_fragments,
+                    100.0 * stats.saturated_fragments as f32 / stats.total_fragments.max(1) as f32
+                ));
+                ui.label(format!("Covered pixels: {}", stats.covered_pixels));
+                ui.label(format!(
+                    "Fragments per covered pixel: {:.1} avg, {} max",
+                    stats.total_fragments as f32 / covered_pixels,
+                    stats.max_fragments
+                ));
+            }
+        }
+    }
+
+    pub fn scene_orientation_panel(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        ui.label("Scene orientation");
+        let mut transform = self.scene_transform;
+        ui.horizontal(|ui| {
+            if ui.button("Y-up").clicked() {
+                transform.set_up(Vec3::y());
+            }
+            if ui.button("Z-up").clicked() {
+                transform.set_up(Vec3::z());
+            }
+            if ui.button("Flip").clicked() {
+                transform.rotate(Vec3::x(), 180.0);
+            }
+            if ui.button("Auto up").clicked() {
+                match scene_transform::estimate_up(
+                    &self.training_cameras,
+                    &self.splat_assets[SCENE_ASSET].gaussians,
+                ) {
+                    Some(up) => transform.set_up(up),
+                    None => {
+                        self.scene_transform_status =
+                            Some("Could not estimate an up vector".to_string())
+                    }
+                }
+            }
+            if ui.button("Reset").clicked() {
+                transform = SceneTransform::default();
+            }
+        });
+        rotate_buttons(ui, |axis, degrees| transform.rotate(axis, degrees));
+        let translation_speed = self.translation_speed();
+        ui.horizontal(|ui| {
+            ui.label("Translation:");
+            for component in &mut transform.translation {
+                ui.add(egui::DragValue::new(component).speed(translation_speed));
+            }
+        });
+        ui.add(
+            egui::Slider::new(&mut transform.scale, 0.001..=1000.0)
+                .logarithmic(true)
+                .text("Scale"),
+        );
+        if transform != self.scene_transform {
+            self.set_scene_transform(world, transform);
+        }
+        if let Some(status) = &self.scene_transform_status {
+            ui.label(status);
+        }
+    }
+
+    pub fn objects_panel(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        let translation_speed = self.translation_speed();
+        if self.splat_objects.len() > 1 {
+            ui.separator();
+            ui.label("Objects");
+        }
+        for (index, (entity, splat)) in self.splat_objects.iter().enumerate().skip(1) {
+            let asset = &self.splat_assets[splat.asset];
+            ui.horizontal(|ui| {
+                if ui
+                    .selectable_label(
+                        self.selected_object == Some(index),
+                        format!(
+                            "{} ({} gaussians, SH degree {})",
+                            asset.name,
+                            asset.gaussians.len(),
+                            asset.sh_degree
+                        ),
+                    )
+                    .clicked()
+                {
+                    self.selected_object = Some(index);
+                }
+                if let Some(visibility) = world.get_visibility_mut(*entity) {
+                    ui.checkbox(&mut visibility.visible, "Visible");
+                }
+            });
+            if self.selected_object != Some(index) {
+                continue;
+            }
+            let Some(mut local_transform) = world.get_local_transform(*entity).copied() else {
+                continue;
+            };
+            rotate_buttons(ui, |axis, degrees| {
+                local_transform.rotation = nalgebra_glm::quat_normalize(
+                    &(nalgebra_glm::quat_angle_axis(degrees.to_radians(), &axis)
+                        * local_transform.rotation),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Translation:");
+                for component in local_transform.translation.iter_mut() {
+                    ui.add(egui::DragValue::new(component).speed(translation_speed));
+                }
+            });
+            let mut scale = local_transform.scale.x;
+            ui.add(
+                egui::Slider::new(&mut scale, 0.001..=1000.0)
+                    .logarithmic(true)
+                    .text("Scale"),
+            );
+            local_transform.scale = Vec3::repeat(scale);
+            if world.get_local_transform(*entity) != Some(&local_transform) {
+                if let Some(current) = world.get_local_transform_mut(*entity) {
+                    *current = local_transform;
+                }
+                mark_local_transform_dirty(world, *entity);
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn merge_panel(&mut self, ui: &mut egui::Ui, world: &World) {
+        if self.splat_objects.len() > 1 {
+            ui.horizontal(|ui| {
+                ui.label("Merge SH degree:");
+                ui.radio_value(&mut self.merge_sh_degree, None, "Max");
+                for degree in 0..=ply::MAX_SH_DEGREE {
+                    ui.radio_value(&mut self.merge_sh_degree, Some(degree), degree.to_string());
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut self.remove_merge_duplicates,
+                    "Remove duplicates within",
+                );
+                ui.add_enabled(
+                    self.remove_merge_duplicates,
+                    egui::DragValue::new(&mut self.merge_dedupe_distance)
+                        .speed(0.001)
+                        .range(0.0001..=10.0),
+                );
+            });
+            if ui
+                .add_enabled(
+                    self.streamer.is_none(),
+                    egui::Button::new("Merge visible objects into PLY"),
+                )
+                .clicked()
+            {
+                self.merge_status = Some(self.merge_objects(world));
+            }
+            if let Some(status) = &self.merge_status {
+                ui.label(status);
+            }
+        }
+    }
+
+    pub fn crop_panel(&mut self, ui: &mut egui::Ui) {
+        let translation_speed = self.translation_speed();
+        ui.horizontal(|ui| {
+            ui.label("Crop volumes");
+            ui.checkbox(&mut self.show_crop_handles, "Show handles");
+        });
+        ui.horizontal(|ui| {
+            let full = self.crop_volumes.len() >= crop::MAX_CROP_VOLUMES;
+            for (label, shape) in [
+                ("Add box", CropShape::Aabb),
+                ("Add oriented box", CropShape::Obb),
+                ("Add sphere", CropShape::Sphere),
+            ] {
+                if ui.add_enabled(!full, egui::Button::new(label)).clicked() {
+                    self.crop_volumes
+                        .push(CropVolume::new(shape, self.scene_bounds.as_ref()));
+                    self.selected_crop = Some(self.crop_volumes.len() - 1);
+                }
+            }
+        });
+        let mut removed_crop = None;
+        for (index, volume) in self.crop_volumes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let shape_label = match volume.shape {
+                    CropShape::Aabb => "Box",
+                    CropShape::Obb => "Oriented box",
+                    CropShape::Sphere => "Sphere",
+                };
+                if ui
+                    .selectable_label(
+                        self.selected_crop == Some(index),
+                        format!("{} {}", shape_label, index + 1),
+                    )
+                    .clicked()
+                {
+                    self.selected_crop = Some(index);
+                }
+                ui.checkbox(&mut volume.enabled, "Enabled");
+                if ui.small_button("Delete").clicked() {
+                    removed_crop = Some(index);
+                }
+            });
+            if self.selected_crop != Some(index) {
+                continue;
+            }
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut volume.shape, CropShape::Aabb, "Box");
+                ui.radio_value(&mut volume.shape, CropShape::Obb, "Oriented box");
+                ui.radio_value(&mut volume.shape, CropShape::Sphere, "Sphere");
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut volume.mode, CropMode::Keep, "Keep inside");
+                ui.radio_value(&mut volume.mode, CropMode::Remove, "Remove inside");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Center:");
+                for component in volume.center.iter_mut() {
+                    ui.add(egui::DragValue::new(component).speed(translation_speed));
+                }
+            });
+            ui.horizontal(|ui| {
+                if volume.shape == CropShape::Sphere {
+                    ui.label("Radius:");
+                    let mut radius = volume.radius();
+                    ui.add(
+                        egui::DragValue::new(&mut radius)
+                            .speed(translation_speed)
+                            .range(0.001..=f32::MAX),
+                    );
+                    volume.half_extents = Vec3::repeat(radius);
+                } else {
+                    ui.label("Half size:");
+                    for component in volume.half_extents.iter_mut() {
+                        ui.add(
+                            egui::DragValue::new(component)
+                                .speed(translation_speed)
+                                .range(0.001..=f32::MAX),
+                        );
+                    }
+                }
+            });
+            if volume.shape == CropShape::Obb {
+                ui.horizontal(|ui| {
+                    ui.label("Rotation:");
+                    for component in volume.rotation_degrees.iter_mut() {
+                        ui.add(egui::DragValue::new(component).speed(0.5).suffix("°"));
+                    }
+                });
+            }
+        }
+        if let Some(index) = removed_crop {
+            self.crop_volumes.remove(index);
+            self.crop_drag = None;
+            self.selected_crop = None;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if ui
+                .add_enabled(
+                    !self.crop_volumes.is_empty() && self.streamer.is_none(),
+                    egui::Button::new("Bake crop"),
+                )
+                .clicked()
+            {
+                self.crop_status =
+                    Some(self.export_ply(&crop::cropped_path(&self.ply_file), true, None));
+            }
+            if let Some(status) = &self.crop_status {
+                ui.label(status);
+            }
+        }
+    }
+
+    pub fn selection_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("Selected: {}", self.selection.count()));
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.selection_mode, None, "Navigate");
+            ui.radio_value(
+                &mut self.selection_mode,
+                Some(SelectionMode::Click),
+                "Click",
+            );
+            ui.radio_value(
+                &mut self.selection_mode,
+                Some(SelectionMode::Rectangle),
+                "Rectangle",
+            );
+            ui.radio_value(
+                &mut self.selection_mode,
+                Some(SelectionMode::Lasso),
+                "Lasso",
+            );
+            ui.radio_value(
+                &mut self.selection_mode,
+                Some(SelectionMode::Brush),
+                "Brush",
+            );
+        });
+        if self.selection_mode == Some(SelectionMode::Brush) {
+            ui.add(
+                egui::Slider::new(&mut self.brush_radius, 0.002..=0.5)
+                    .logarithmic(true)
+                    .text("Brush radius (scene radii)"),
+            );
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Clear selection").clicked() {
+                self.selection.clear();
+            }
+            if ui.button("Invert").clicked() {
+                self.selection.invert(|index| self.edits.is_active(index));
+            }
+        });
+        if self.selection_mode.is_some() {
+            ui.label("Shift adds to the selection, Alt removes from it");
+        }
+        ui.horizontal(|ui| {
+            ui.label("Transform:");
+            ui.radio_value(&mut self.gizmo_mode, None, "Off");
+            ui.radio_value(&mut self.gizmo_mode, Some(GizmoMode::Translate), "Move");
+            ui.radio_value(&mut self.gizmo_mode, Some(GizmoMode::Rotate), "Rotate");
+            ui.radio_value(&mut self.gizmo_mode, Some(GizmoMode::Scale), "Scale");
+        });
+    }
+
+    pub fn edit_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let has_selection = self.selection.count() > 0;
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Delete (Del)"))
+                .clicked()
+            {
+                self.edit_selection("Delete", GaussianState::Deleted);
+            }
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Hide (H)"))
+                .clicked()
+            {
+                self.edit_selection("Hide", GaussianState::Hidden);
+            }
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Isolate (I)"))
+                .clicked()
+            {
+                self.isolate_selection();
+            }
+            if ui
+                .add_enabled(
+                    self.edits.hidden_count() > 0,
+                    egui::Button::new("Unhide all (Shift+H)"),
+                )
+                .clicked()
+            {
+                self.unhide_all();
+            }
+        });
+        ui.horizontal(|ui| {
+            let undo_label = self
+                .edits
+                .undo_label()
+                .map_or("Undo".to_string(), |label| format!("Undo {label}"));
+            if ui
+                .add_enabled(
+                    self.edits.undo_label().is_some(),
+                    egui::Button::new(format!("{undo_label} (Ctrl+Z)")),
+                )
+                .clicked()
+            {
+                self.undo();
+            }
+            let redo_label = self
+                .edits
+                .redo_label()
+                .map_or("Redo".to_string(), |label| format!("Redo {label}"));
+            if ui
+                .add_enabled(
+                    self.edits.redo_label().is_some(),
+                    egui::Button::new(format!("{redo_label} (Ctrl+Y)")),
+                )
+                .clicked()
+            {
+                self.redo();
+            }
+        });
+        ui.label(format!(
+            "Hidden: {}, deleted: {}",
+            self.edits.hidden_count(),
+            self.edits.deleted_count()
+        ));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.checkbox(
+                &mut self.bake_scene_transform,
+                "Bake scene transform into exports",
+            );
+            if ui
+                .add_enabled(
+                    self.streamer.is_none(),
+                    egui::Button::new("Save edited PLY"),
+                )
+                .clicked()
+            {
+                self.edit_status =
+                    Some(self.export_ply(&edits::edited_path(&self.ply_file), false, None));
+            }
+            if let Some(status) = &self.edit_status {
+                ui.label(status);
+            }
+        }
+    }
+
+    pub fn filter_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label("Filters");
+        let radius = self.scene_bounds.map_or(1.0, |bounds| bounds.radius());
+        let settings = &mut self.filter_settings;
+        optional_filter(
+            ui,
+            "Outliers beyond std devs",
+            &mut settings.outlier_std_ratio,
+            DEFAULT_OUTLIER_STD_RATIO,
+            0.1..=10.0,
+        );
+        if settings.outlier_std_ratio.is_some() {
+            ui.add(
+                egui::Slider::new(
+                    &mut settings.outlier_neighbors,
+                    1..=filter::MAX_OUTLIER_NEIGHBORS,
+                )
+                .text("Neighbors"),
+            );
+        }
+        optional_filter(
+            ui,
+            "Min opacity",
+            &mut settings.min_opacity,
+            DEFAULT_MIN_OPACITY,
+            0.0..=1.0,
+        );
+        optional_filter(
+            ui,
+            "Max scale",
+            &mut settings.max_scale,
+            radius * DEFAULT_MAX_SCALE_RADII,
+            radius * 0.001..=radius,
+        );
+        optional_filter(
+            ui,
+            "Max aspect ratio",
+            &mut settings.max_aspect_ratio,
+            DEFAULT_MAX_ASPECT_RATIO,
+            1.0..=1000.0,
+        );
+        ui.horizontal(|ui| {
+            ui.label(format!("Filtered: {}", self.edits.filtered_count()));
+            if self.neighbor_distances_pending() {
+                ui.label("Computing neighbor distances...");
+            }
+            if ui
+                .add_enabled(
+                    self.edits.filtered_count() > 0,
+                    egui::Button::new("Delete filtered"),
+                )
+                .clicked()
+            {
+                self.delete_filtered();
+            }
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.checkbox(&mut self.apply_filters_on_save, "Apply filters on save");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn decimate_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Decimate to");
+            ui.add_enabled(
+                self.streamer.is_none(),
+                egui::DragValue::new(&mut self.decimate_target)
+                    .range(1..=self.gaussian_count.max(1))
+                    .speed(1000.0),
+            );
+            ui.label("splats");
+        });
+        if ui
+            .add_enabled(
+                self.streamer.is_none(),
+                egui::Button::new("Save decimated PLY"),
+            )
+            .clicked()
+        {
+            self.decimate_status = Some(self.export_ply(
+                &decimate::decimated_path(&self.ply_file),
+                false,
+                Some(self.decimate_target),
+            ));
+        }
+        if let Some(status) = &self.decimate_status {
+            ui.label(status);
+        }
+    }
+
+    pub fn camera_path_panel(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        match self.camera_path.time_range() {
+            Some((start, end)) => ui.label(format!(
+                "Camera path: {} keyframes, {:.1} s",
+                self.camera_path.keyframes.len(),
+                end - start
+            )),
+            None => ui.label("Camera path: no keyframes"),
+        };
+        ui.horizontal(|ui| {
+            if ui.button("Add keyframe").clicked()
+                && let Some(pose) = current_camera_pose(world)
+            {
+                let time = self
+                    .camera_path
+                    .time_range()
+                    .map_or(0.0, |(_, end)| end + KEYFRAME_SPACING_SECONDS);
+                self.camera_path
+                    .push(CameraKeyframe::from_pose(time, &pose));
+            }
+            if ui.button("Turntable").clicked()
+                && let Some(entity) = world.resources.active_camera
+                && let Some(pan_orbit) = world.get_pan_orbit_camera(entity)
+                && let Some(pose) = current_camera_pose(world)
+            {
+                self.camera_path = CameraPath::turntable(
+                    pan_orbit.focus,
+                    pan_orbit.radius,
+                    pan_orbit.pitch,
+                    pose.y_fov_rad,
+                    TURNTABLE_SECONDS,
+                );
+            }
+            if ui.button("Clear").clicked() {
+                self.stop_camera_path_preview(world);
+                self.camera_path = CameraPath::default();
+            }
+        });
+        ui.horizontal(|ui| {
+            if self.camera_path_preview_time.is_some() {
+                if ui.button("Stop preview").clicked() {
+                    self.stop_camera_path_preview(world);
+                }
+            } else if ui
+                .add_enabled(
+                    !self.camera_path.keyframes.is_empty(),
+                    egui::Button::new("Preview"),
+                )
+                .clicked()
+            {
+                self.start_camera_path_preview();
+            }
+            if let Some(file) = &self.camera_path_file
+                && ui.button("Save").clicked()
+            {
+                self.camera_path_status = Some(
+                    match camera_path::save_camera_path(file, &self.camera_path) {
+                        Ok(()) => format!("Saved {}", file.display()),
+                        Err(error) => format!("Failed to save {}: {}", file.display(), error),
+                    },
+                );
+            }
+        });
+        if let Some(status) = &self.camera_path_status {
+            ui.label(status);
+        }
+    }
+
+    pub fn bookmark_panel(&mut self, ui: &mut egui::Ui, world: &World) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.bookmark_name)
+                    .hint_text("Bookmark name")
+                    .desired_width(140.0),
+            );
+            if ui.button("Add bookmark").clicked() {
+                self.add_bookmark(world);
+            }
+        });
+        let mut recalled = None;
+        let mut removed = None;
+        for (index, bookmark) in self.bookmarks.bookmarks.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let label = if index < BOOKMARK_KEYS.len() {
+                    format!("{}: {}", index + 1, bookmark.name)
+                } else {
+                    bookmark.name.clone()
+                };
+                if ui.button(label).clicked() {
+                    recalled = Some(index);
+                }
+                ui.label(format!("FOV {:.0}", bookmark.fov_degrees));
+                if ui.small_button("Delete").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = recalled {
+            self.recall_bookmark(world, index);
+        }
+        if let Some(index) = removed {
+            self.bookmarks.bookmarks.remove(index);
+            self.save_bookmarks();
+        }
+        if let Some(status) = &self.bookmark_status {
+            ui.label(status);
+        }
+    }
+
+    pub fn training_view_panel(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        if !self.training_cameras.is_empty() {
+            ui.separator();
+            ui.checkbox(&mut self.show_training_frusta, "Show training cameras");
+            match self
+                .training_view
+                .and_then(|index| Some((index, self.training_cameras.get(index)?)))
+            {
+                Some((index, camera)) => ui.label(format!(
+                    "Training view {}/{}: {} (id {}, {}x{})",
+                    index + 1,
+                    self.training_cameras.len(),
+                    camera.img_name,
+                    camera.id,
+                    camera.width,
+                    camera.height
+                )),
+                None => ui.label(format!(
+                    "{} training views (PageUp/PageDown to step)",
+                    self.training_cameras.len()
+                )),
+            };
+            ui.horizontal(|ui| {
+                if ui.button("Previous").clicked() {
+                    self.step_training_view(world, -1);
+                }
+                if ui.button("Next").clicked() {
+                    self.step_training_view(world, 1);
+                }
+                if ui
+                    .add_enabled(self.training_view.is_some(), egui::Button::new("Orbit"))
+                    .clicked()
+                {
+                    self.select_training_view(world, None);
+                }
+            });
+        }
+    }
+}
+
+fn rotate_buttons(ui: &mut egui::Ui, mut rotate: impl FnMut(Vec3, f32)) {
+    for (label, axis) in [("X", Vec3::x()), ("Y", Vec3::y()), ("Z", Vec3::z())] {
+        ui.horizontal(|ui| {
+            ui.label(format!("Rotate {label}:"));
+            for degrees in [-90.0, -5.0, 5.0, 90.0] {
+                if ui.button(format!("{degrees:+}")).clicked() {
+                    rotate(axis, degrees);
+                }
+            }
+        });
+    }
+}
+
+fn optional_filter(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<f32>,
+    default: f32,
+    range: std::ops::RangeInclusive<f32>,
+) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *value = enabled.then_some(default);
+        }
+        if let Some(value) = value {
+            ui.add(egui::Slider::new(value, range).logarithmic(true));
+        }
+    });
+}